@@ -0,0 +1,269 @@
+//! Замеры горячих мест конвейера: предобработка кадра, разбор выхода YOLO, NMS, IoU
+//! и обновление каждого трекера на синтетических кадрах.
+//!
+//! `cargo bench` — запуск; Criterion хранит результаты в `target/criterion` и сравнивает
+//! с предыдущим прогоном. Для сравнения с зафиксированной точкой:
+//! `cargo bench -- --save-baseline main` до изменений и `cargo bench -- --baseline main` после.
+
+// Крейт бинарный, поэтому нужные модули подключаются напрямую.
+// Бенчмарк использует лишь часть каждого модуля — остальное для него мёртвый код.
+#[allow(dead_code)]
+#[path = "../src/config.rs"]
+mod config;
+#[allow(dead_code)]
+#[path = "../src/dnn_backend.rs"]
+mod dnn_backend;
+#[allow(dead_code)]
+#[path = "../src/fallback_tracker.rs"]
+mod fallback_tracker;
+#[allow(dead_code)]
+#[path = "../src/kcftracker.rs"]
+mod kcftracker;
+#[allow(dead_code)]
+#[path = "../src/trackers.rs"]
+mod trackers;
+#[allow(dead_code)]
+#[path = "../src/utils.rs"]
+mod utils;
+#[allow(dead_code)]
+#[path = "../src/vit_tracker.rs"]
+mod vit_tracker;
+#[allow(dead_code)]
+#[path = "../src/yolo.rs"]
+mod yolo;
+
+use crate::config::{DetectorConfig, Preprocess, TrackerKind, TrackersConfig};
+use crate::trackers::{Tracker, create_tracker};
+use crate::utils::{BBox, iou, letterbox, mat_to_ndarray};
+use crate::yolo::{InputTransform, YoloV8, nms};
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use ndarray::{ArrayD, IxDyn};
+use opencv::core::{self, Mat, Point, Rect, Scalar};
+use opencv::imgproc;
+use opencv::prelude::*;
+use std::hint::black_box;
+
+/// Разрешения камеры, с которых кадр приводится ко входу сети
+const RESOLUTIONS: [(i32, i32); 4] = [(320, 240), (640, 480), (1280, 720), (1632, 1232)];
+const INPUT_SIZE: i32 = 640;
+/// Якорей у YOLOv8 при входе 640
+const ANCHORS: usize = 8400;
+
+/// Детерминированный генератор, чтобы входы не менялись между прогонами.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+fn noise_frame(width: i32, height: i32) -> Mat {
+    core::set_rng_seed(42).unwrap();
+    let mut frame =
+        Mat::new_rows_cols_with_default(height, width, core::CV_8UC3, Scalar::all(0.0)).unwrap();
+    core::randu(&mut frame, &Scalar::all(0.0), &Scalar::all(255.0)).unwrap();
+    frame
+}
+
+/// Кадр с целью-мишенью в `target` поверх шума.
+fn target_frame(width: i32, height: i32, target: Rect) -> Mat {
+    let mut frame = noise_frame(width, height);
+    imgproc::rectangle(
+        &mut frame,
+        target,
+        Scalar::new(40.0, 200.0, 240.0, 0.0),
+        imgproc::FILLED,
+        imgproc::LINE_8,
+        0,
+    )
+    .unwrap();
+    let center = Point::new(target.x + target.width / 2, target.y + target.height / 2);
+    imgproc::circle(
+        &mut frame,
+        center,
+        target.width.min(target.height) / 3,
+        Scalar::new(20.0, 20.0, 160.0, 0.0),
+        4,
+        imgproc::LINE_8,
+        0,
+    )
+    .unwrap();
+    frame
+}
+
+/// Выход сети в форме `[anchors, 4 + classes, 1]`, как после `YoloV8::inference`;
+/// примерно каждый сотый якорь уверенный.
+fn synthetic_output() -> ArrayD<f32> {
+    let mut rng = Lcg(7);
+    let rows = 4 + yolo::COCO_CLASSES.len();
+    let mut data = Vec::with_capacity(ANCHORS * rows);
+    for _ in 0..ANCHORS {
+        let confident = rng.next() < 0.01;
+        data.extend([
+            rng.next() * 640.0,
+            rng.next() * 640.0,
+            8.0 + rng.next() * 200.0,
+            8.0 + rng.next() * 200.0,
+        ]);
+        for _ in 0..yolo::COCO_CLASSES.len() {
+            let score = rng.next() * 0.1;
+            data.push(if confident && rng.next() < 0.05 {
+                0.5 + score * 5.0
+            } else {
+                score
+            });
+        }
+    }
+    ArrayD::from_shape_vec(IxDyn(&[ANCHORS, rows, 1]), data).unwrap()
+}
+
+fn random_boxes(count: usize) -> Vec<BBox> {
+    let mut rng = Lcg(11);
+    (0..count)
+        .map(|_| {
+            // Скопления вокруг нескольких центров, чтобы NMS было что подавлять
+            let (cx, cy) = (
+                (rng.next() * 8.0).floor() * 80.0,
+                (rng.next() * 6.0).floor() * 80.0,
+            );
+            let (x, y) = (cx + rng.next() * 20.0, cy + rng.next() * 20.0);
+            let (w, h) = (40.0 + rng.next() * 20.0, 40.0 + rng.next() * 20.0);
+            BBox {
+                x1: x,
+                y1: y,
+                x2: x + w,
+                y2: y + h,
+                class_id: (rng.next() * 3.0) as usize,
+                confidence: rng.next(),
+            }
+        })
+        .collect()
+}
+
+fn preprocess(c: &mut Criterion) {
+    let mut group = c.benchmark_group("preprocess");
+    for (width, height) in RESOLUTIONS {
+        let frame = noise_frame(width, height);
+        let id = format!("{}x{}", width, height);
+        group.bench_with_input(
+            BenchmarkId::new("mat_to_ndarray", &id),
+            &frame,
+            |b, frame| b.iter(|| mat_to_ndarray(black_box(frame), INPUT_SIZE, INPUT_SIZE)),
+        );
+        group.bench_with_input(BenchmarkId::new("letterbox", &id), &frame, |b, frame| {
+            b.iter(|| {
+                mat_to_ndarray(
+                    &letterbox(black_box(frame), INPUT_SIZE).unwrap(),
+                    INPUT_SIZE,
+                    INPUT_SIZE,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn detector(c: &mut Criterion) {
+    let mut group = c.benchmark_group("detector");
+    let output = synthetic_output();
+    let transform = InputTransform::new(Preprocess::Stretch, INPUT_SIZE, 1632, 1232);
+    group.bench_function("postprocess", |b| {
+        b.iter(|| YoloV8::postprocess(black_box(&output), &transform, 0.5))
+    });
+
+    // Полный прогон сети — только если модель на месте
+    let config = DetectorConfig::default();
+    match YoloV8::new(&config) {
+        Ok(mut yolo) => {
+            let size = yolo.input_size.unwrap_or(INPUT_SIZE);
+            let input = mat_to_ndarray(&noise_frame(1632, 1232), size, size);
+            group.sample_size(20);
+            group.bench_function("infer2", |b| {
+                b.iter(|| {
+                    yolo.infer2(black_box(&input), 1632, 1232)
+                        .expect("Inference failed")
+                })
+            });
+        }
+        Err(err) => eprintln!("skip detector/infer2: {}", err),
+    }
+    group.finish();
+}
+
+fn geometry(c: &mut Criterion) {
+    let mut group = c.benchmark_group("geometry");
+    for count in [100, 1000] {
+        let boxes = random_boxes(count);
+        group.bench_with_input(BenchmarkId::new("nms", count), &boxes, |b, boxes| {
+            b.iter_batched(
+                || boxes.clone(),
+                |boxes| nms(boxes, 0.45),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    let (a, b) = (Rect::new(10, 20, 100, 80), Rect::new(50, 40, 120, 90));
+    group.bench_function("iou", |bench| {
+        bench.iter(|| iou(black_box(&a), black_box(&b)))
+    });
+    let boxes = random_boxes(2);
+    group.bench_function("bbox_iou", |bench| {
+        bench.iter(|| black_box(&boxes[0]).iou(black_box(&boxes[1])))
+    });
+    group.finish();
+}
+
+fn tracker_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tracker_update");
+    group.sample_size(20);
+    let (width, height) = (1632, 1232);
+    let start = Rect::new(780, 580, 72, 56);
+    // Цель смещается туда и обратно, чтобы трекер не стоял на месте
+    let frames = [
+        target_frame(width, height, start),
+        target_frame(
+            width,
+            height,
+            Rect::new(start.x + 4, start.y + 2, start.width, start.height),
+        ),
+    ];
+
+    for kind in [
+        TrackerKind::Nano,
+        TrackerKind::Vit,
+        TrackerKind::VitInt8,
+        TrackerKind::Dasiamrpn,
+        TrackerKind::Kcf,
+    ] {
+        let config = TrackersConfig {
+            primary: kind,
+            fallback: None,
+            ..TrackersConfig::default()
+        };
+        let mut tracker = match create_tracker(&config)
+            .and_then(|mut tracker| tracker.init(&frames[0], start).map(|_| tracker))
+        {
+            Ok(tracker) => tracker,
+            Err(err) => {
+                eprintln!("skip tracker_update/{}: {}", kind.name(), err);
+                continue;
+            }
+        };
+        let mut index = 0;
+        group.bench_function(kind.name(), |b| {
+            b.iter(|| {
+                index ^= 1;
+                tracker.update(&frames[index]).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, preprocess, detector, geometry, tracker_update);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+use crate::config::CameraConfig;
+use opencv::core::Rect;
+
+/// Смещение центра цели от центра кадра.
+///
+/// Пиксели: x вправо, y вниз. Углы: `yaw_deg` положителен вправо,
+/// `pitch_deg` — вверх, как принято для подвесов.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TargetOffset {
+    pub dx_px: f32,
+    pub dy_px: f32,
+    pub yaw_deg: f32,
+    pub pitch_deg: f32,
+}
+
+/// Модель камеры-обскуры с известными углами обзора.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    hfov_deg: f32,
+    vfov_deg: f32,
+}
+
+impl Camera {
+    pub fn new(config: &CameraConfig) -> Self {
+        Self {
+            hfov_deg: config.hfov_deg,
+            vfov_deg: config.vfov_deg,
+        }
+    }
+
+    /// Фокусное расстояние в пикселях для стороны `size` и угла обзора `fov_deg`.
+    fn focal_px(size: i32, fov_deg: f32) -> f32 {
+        size as f32 / 2.0 / (fov_deg.to_radians() / 2.0).tan()
+    }
+
+    pub fn offset(&self, bbox: Rect, width: i32, height: i32) -> TargetOffset {
+        let dx = bbox.x as f32 + bbox.width as f32 / 2.0 - width as f32 / 2.0;
+        let dy = bbox.y as f32 + bbox.height as f32 / 2.0 - height as f32 / 2.0;
+
+        TargetOffset {
+            dx_px: dx,
+            dy_px: dy,
+            yaw_deg: (dx / Self::focal_px(width, self.hfov_deg))
+                .atan()
+                .to_degrees(),
+            pitch_deg: -(dy / Self::focal_px(height, self.vfov_deg))
+                .atan()
+                .to_degrees(),
+        }
+    }
+
+    /// Угловые размеры рамки по горизонтали и вертикали, градусы.
+    pub fn angular_size(&self, bbox: Rect, width: i32, height: i32) -> (f32, f32) {
+        let angle = |size: i32, side: i32, fov_deg: f32| {
+            (2.0 * (size as f32 / 2.0 / Self::focal_px(side, fov_deg)).atan()).to_degrees()
+        };
+        (
+            angle(bbox.width, width, self.hfov_deg),
+            angle(bbox.height, height, self.vfov_deg),
+        )
+    }
+}
@@ -0,0 +1,795 @@
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Переменная окружения с путём к файлу конфигурации.
+pub const CONFIG_ENV: &str = "NANO_CONFIG";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub detector: DetectorConfig,
+    pub trackers: TrackersConfig,
+    pub template_refresh: TemplateRefreshConfig,
+    pub logging: LoggingConfig,
+    pub profiling: ProfilingConfig,
+    pub metrics: MetricsConfig,
+    pub thermal: ThermalConfig,
+    pub sysmon: SysmonConfig,
+    pub overlay: OverlayConfig,
+    pub camera: CameraConfig,
+    pub input: InputConfig,
+    pub control: ControlConfig,
+    pub telemetry: TelemetryConfig,
+    pub mavlink: MavlinkConfig,
+    pub gimbal: GimbalConfig,
+    pub recording: RecordingConfig,
+}
+
+impl AppConfig {
+    /// Читает конфиг из `$NANO_CONFIG` или `config.toml` в корне проекта.
+    /// Если файла нет — используются значения по умолчанию.
+    pub fn load() -> Self {
+        let path = std::env::var(CONFIG_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| Path::new(env!("CARGO_MANIFEST_DIR")).join("config.toml"));
+
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .unwrap_or_else(|err| panic!("Can't parse config {}: {}", path.display(), err)),
+            Err(_) => {
+                eprintln!("Config {} not found, using defaults", path.display());
+                Self::default()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    Sequential,
+    Parallel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionProvider {
+    Cpu,
+    Xnnpack,
+    Acl,
+    Armnn,
+}
+
+/// Как кадр приводится к квадратному входу сети.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preprocess {
+    /// Растянуть без сохранения пропорций
+    Stretch,
+    /// Вписать с сохранением пропорций и серыми полями
+    Letterbox,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DetectorConfig {
+    pub model: String,
+    /// Сторона квадратного входа сети
+    pub input_size: i32,
+    /// 0 — оставить решение за ONNX Runtime.
+    pub intra_threads: usize,
+    pub inter_threads: usize,
+    pub intra_op_spinning: bool,
+    pub execution_mode: ExecutionMode,
+    /// Провайдеры в порядке приоритета; CPU всегда остаётся запасным вариантом.
+    pub providers: Vec<ExecutionProvider>,
+    /// Файл для кэша оптимизированной модели (ускоряет холодный старт).
+    pub optimized_model_cache: Option<String>,
+    pub preprocess: Preprocess,
+    /// Минимальная уверенность детекции
+    pub confidence_threshold: f32,
+    /// Порог IoU для NMS по классам; без него пересекающиеся рамки не подавляются.
+    pub nms_iou_threshold: Option<f32>,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            model: "yolov8n.onnx".to_string(),
+            input_size: 640,
+            intra_threads: 0,
+            inter_threads: 0,
+            intra_op_spinning: true,
+            execution_mode: ExecutionMode::Sequential,
+            providers: vec![ExecutionProvider::Cpu],
+            optimized_model_cache: None,
+            preprocess: Preprocess::Stretch,
+            confidence_threshold: 0.5,
+            nms_iou_threshold: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnnBackend {
+    Opencv,
+    /// OpenVINO
+    InferenceEngine,
+    Timvx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnnTarget {
+    Cpu,
+    CpuFp16,
+    Opencl,
+    OpenclFp16,
+    Npu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DnnConfig {
+    pub backend: DnnBackend,
+    pub target: DnnTarget,
+}
+
+impl Default for DnnConfig {
+    fn default() -> Self {
+        Self {
+            backend: DnnBackend::Opencv,
+            target: DnnTarget::Cpu,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackerKind {
+    Nano,
+    Vit,
+    VitInt8,
+    Dasiamrpn,
+    Kcf,
+}
+
+impl TrackerKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TrackerKind::Nano => "nano",
+            TrackerKind::Vit => "vit",
+            TrackerKind::VitInt8 => "vit_int8",
+            TrackerKind::Dasiamrpn => "dasiamrpn",
+            TrackerKind::Kcf => "kcf",
+        }
+    }
+}
+
+/// Что делать, когда основной трекер опустился ниже порога.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackPolicy {
+    /// Переинициализировать основной трекер по результату запасного
+    ReinitPrimary,
+    /// Продолжать на запасном, пока он держит цель
+    StayOnFallback,
+    /// Не использовать запасной — цель потеряна
+    GiveUp,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TrackersConfig {
+    pub primary: TrackerKind,
+    /// `"none"` — без запасного трекера
+    #[serde(deserialize_with = "fallback_kind")]
+    pub fallback: Option<TrackerKind>,
+    pub primary_threshold: f32,
+    pub fallback_threshold: f32,
+    pub policy: FallbackPolicy,
+
+    /// Бэкенд OpenCV DNN для каждой модели трекеров.
+    pub nano: DnnConfig,
+    pub vit: DnnConfig,
+    pub dasiamrpn: DnnConfig,
+}
+
+impl Default for TrackersConfig {
+    fn default() -> Self {
+        Self {
+            primary: TrackerKind::VitInt8,
+            fallback: Some(TrackerKind::Vit),
+            primary_threshold: 0.45,
+            fallback_threshold: 0.55,
+            policy: FallbackPolicy::ReinitPrimary,
+            nano: DnnConfig::default(),
+            vit: DnnConfig::default(),
+            dasiamrpn: DnnConfig::default(),
+        }
+    }
+}
+
+/// Запасной трекер или `"none"`: из-за `#[serde(default)]` отсутствие ключа
+/// означает трекер по умолчанию, а не его отключение.
+fn fallback_kind<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<TrackerKind>, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "none" => Ok(None),
+        name => TrackerKind::deserialize(name.into_deserializer()).map(Some),
+    }
+}
+
+/// Периодическое обновление шаблона трекера на текущей рамке.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TemplateRefreshConfig {
+    pub enabled: bool,
+    pub min_score: f32,
+    /// Сколько последних кадров рамка должна быть стабильной
+    pub stable_frames: usize,
+    /// Допустимый сдвиг центра относительно диагонали рамки
+    pub max_center_shift: f32,
+    /// Допустимое относительное изменение площади рамки
+    pub max_scale_change: f32,
+    /// Минимальный IoU с ближайшей детекцией YOLO
+    pub min_detector_iou: f32,
+    pub cooldown_frames: u32,
+}
+
+impl Default for TemplateRefreshConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_score: 0.6,
+            stable_frames: 5,
+            max_center_shift: 0.1,
+            max_scale_change: 0.15,
+            min_detector_iou: 0.6,
+            cooldown_frames: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Уровни в формате `info,nano_plus_gstreamer::yolo=debug`
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProfilingConfig {
+    pub enabled: bool,
+    /// Сколько последних замеров каждой стадии учитывать
+    pub window: usize,
+    pub report_interval_secs: f64,
+    /// Отчёты в JSON Lines
+    pub report_file: Option<String>,
+    /// Chrome trace JSON для chrome://tracing / Perfetto
+    pub trace_file: Option<String>,
+}
+
+impl Default for ProfilingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window: 300,
+            report_interval_secs: 10.0,
+            report_file: None,
+            trace_file: None,
+        }
+    }
+}
+
+/// HTTP-эндпоинт `/metrics` в формате Prometheus.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub listen: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:9100".to_string(),
+        }
+    }
+}
+
+/// Ступень троттлинга; незаданные поля наследуются от более холодных ступеней.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ThermalLevel {
+    pub temp_c: f32,
+    pub detect_every: Option<u32>,
+    pub tracker: Option<TrackerKind>,
+    /// Требует модель детектора с динамическим размером входа, иначе игнорируется
+    pub detector_input: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThermalConfig {
+    pub enabled: bool,
+    pub check_interval_secs: f64,
+    pub hysteresis_c: f32,
+    pub levels: Vec<ThermalLevel>,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_secs: 2.0,
+            hysteresis_c: 5.0,
+            levels: vec![
+                ThermalLevel {
+                    temp_c: 70.0,
+                    detect_every: Some(2),
+                    ..ThermalLevel::default()
+                },
+                ThermalLevel {
+                    temp_c: 80.0,
+                    detect_every: Some(4),
+                    tracker: Some(TrackerKind::VitInt8),
+                    ..ThermalLevel::default()
+                },
+            ],
+        }
+    }
+}
+
+/// Фоновый опрос CPU, памяти, термозон и частот для оверлея и метрик.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SysmonConfig {
+    pub interval_secs: f64,
+}
+
+impl Default for SysmonConfig {
+    fn default() -> Self {
+        Self { interval_secs: 1.0 }
+    }
+}
+
+/// Цвет в порядке BGR, как в OpenCV.
+pub type Color = [f64; 3];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OverlayColors {
+    pub target: Color,
+    pub detections: Color,
+    pub trail: Color,
+    pub search_roi: Color,
+    pub text: Color,
+    /// Курсор и рамка, которую тянет оператор
+    pub selection: Color,
+}
+
+impl Default for OverlayColors {
+    fn default() -> Self {
+        Self {
+            target: [0.0, 255.0, 0.0],
+            detections: [255.0, 200.0, 0.0],
+            trail: [0.0, 200.0, 255.0],
+            search_roi: [160.0, 160.0, 160.0],
+            text: [0.0, 0.0, 255.0],
+            selection: [255.0, 255.0, 255.0],
+        }
+    }
+}
+
+/// Слои оверлея и их оформление.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OverlayConfig {
+    pub target: bool,
+    pub detections: bool,
+    pub track_id: bool,
+    pub trail: bool,
+    pub search_roi: bool,
+    pub state: bool,
+    pub fps: bool,
+    pub system: bool,
+
+    /// Сколько последних центров цели рисовать в следе
+    pub trail_length: usize,
+    /// Отступ области поиска вокруг рамки, пиксели
+    pub search_roi_margin: i32,
+    pub font_scale: f64,
+    pub thickness: i32,
+    /// Высота кадра, для которой заданы шрифт, толщина и позиции
+    pub reference_height: i32,
+    pub system_position: [i32; 2],
+    pub state_position: [i32; 2],
+    pub fps_position: [i32; 2],
+    pub colors: OverlayColors,
+    pub hud: HudConfig,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            target: true,
+            detections: false,
+            track_id: true,
+            trail: false,
+            search_roi: false,
+            state: true,
+            fps: true,
+            system: true,
+            trail_length: 30,
+            search_roi_margin: 100,
+            font_scale: 1.0,
+            thickness: 2,
+            reference_height: 1232,
+            system_position: [30, 50],
+            state_position: [30, 90],
+            fps_position: [30, 130],
+            colors: OverlayColors::default(),
+            hud: HudConfig::default(),
+        }
+    }
+}
+
+/// HUD оператора: прицельная марка, смещение цели, вектор движения, индикатор захвата.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HudConfig {
+    pub enabled: bool,
+    /// Половина размаха перекрестия в пикселях опорного кадра
+    pub reticle_size: i32,
+    /// Сколько кадров подряд цель должна вестись, чтобы считаться захваченной
+    pub lock_frames: u32,
+    /// Сглаживание скорости цели, 0 — без сглаживания
+    pub motion_smoothing: f32,
+    /// Длина стрелки — смещение цели за столько кадров
+    pub arrow_frames: f32,
+    pub text_position: [i32; 2],
+    pub reticle_color: Color,
+    pub motion_color: Color,
+    pub locked_color: Color,
+    pub acquiring_color: Color,
+    pub lost_color: Color,
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reticle_size: 40,
+            lock_frames: 5,
+            motion_smoothing: 0.6,
+            arrow_frames: 5.0,
+            text_position: [30, 200],
+            reticle_color: [0.0, 255.0, 255.0],
+            motion_color: [255.0, 0.0, 255.0],
+            locked_color: [0.0, 255.0, 0.0],
+            acquiring_color: [0.0, 165.0, 255.0],
+            lost_color: [0.0, 0.0, 255.0],
+        }
+    }
+}
+
+/// Углы обзора камеры для пересчёта смещения цели в градусы.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub hfov_deg: f32,
+    pub vfov_deg: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        // Raspberry Pi Camera v2 (IMX219)
+        Self {
+            hfov_deg: 62.2,
+            vfov_deg: 48.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputBackend {
+    None,
+    /// Сенсорный экран, мышь и клавиатура из `/dev/input` (вывод через kmssink)
+    Evdev,
+    /// Окно OpenCV с мышью и клавиатурой
+    Highgui,
+}
+
+/// Выбор цели оператором.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    pub backend: InputBackend,
+    /// Устройства evdev; пусто — все `/dev/input/event*`
+    pub devices: Vec<String>,
+    /// Захватывать первую детекцию без участия оператора
+    pub auto_acquire: bool,
+    /// Меньшее перемещение считается кликом, а не рамкой
+    pub min_drag_px: i32,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            backend: InputBackend::None,
+            devices: Vec::new(),
+            auto_acquire: true,
+            min_drag_px: 10,
+        }
+    }
+}
+
+/// Управляющий сокет: команды и события в виде JSON-строк.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    pub socket: String,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            socket: "/tmp/nano_plus_gstreamer.sock".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetryFormat {
+    /// JSON Lines
+    Json,
+    /// Компактные записи, формат описан в `telemetry.rs`
+    Binary,
+}
+
+/// Покадровые результаты трекинга для автопилота и систем логирования.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Адрес получателя UDP, например `127.0.0.1:5600`
+    pub udp: Option<String>,
+    pub udp_format: TelemetryFormat,
+    /// Файл, в который записи дописываются подряд
+    pub file: Option<String>,
+    pub file_format: TelemetryFormat,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            udp: Some("127.0.0.1:5600".to_string()),
+            udp_format: TelemetryFormat::Binary,
+            file: None,
+            file_format: TelemetryFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MavlinkLink {
+    Udp,
+    Serial,
+    /// Кадры разбираются обратно и пишутся в лог, без автопилота
+    Loopback,
+}
+
+/// Передача цели автопилоту и подвесу по MAVLink v2.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MavlinkConfig {
+    pub enabled: bool,
+    pub link: MavlinkLink,
+    pub udp: String,
+    pub serial: String,
+    pub baud: u32,
+    pub system_id: u8,
+    pub component_id: u8,
+    /// Получатель GIMBAL_MANAGER_SET_PITCHYAW
+    pub target_system: u8,
+    pub target_component: u8,
+    /// 0 — все подвесы
+    pub gimbal_device_id: u8,
+    pub heartbeat_interval_secs: f64,
+    /// Отправлять LANDING_TARGET
+    pub landing_target: bool,
+    /// Отправлять GIMBAL_MANAGER_SET_PITCHYAW
+    pub gimbal: bool,
+    /// Скорость подвеса, рад/с на радиан смещения цели
+    pub rate_gain: f32,
+    pub max_rate_deg: f32,
+}
+
+impl Default for MavlinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            link: MavlinkLink::Udp,
+            udp: "127.0.0.1:14550".to_string(),
+            serial: "/dev/ttyTHS1".to_string(),
+            baud: 115200,
+            system_id: 1,
+            // MAV_COMP_ID_CAMERA
+            component_id: 100,
+            target_system: 1,
+            target_component: 1,
+            gimbal_device_id: 0,
+            heartbeat_interval_secs: 1.0,
+            landing_target: true,
+            gimbal: true,
+            rate_gain: 1.5,
+            max_rate_deg: 60.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct PidGains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActuatorKind {
+    /// Модель привода без железа
+    Sim,
+    /// SimpleBGC serial API по UART
+    Sbgc,
+    PelcoD,
+}
+
+/// Что делает подвес, когда цели нет.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LostBehavior {
+    /// Остановиться там, где потеряли цель
+    Hold,
+    /// Через `center_after_secs` вернуться в нулевое положение
+    Center,
+}
+
+/// ПИД-управление поворотным подвесом по смещению цели.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GimbalConfig {
+    pub enabled: bool,
+    pub actuator: ActuatorKind,
+    pub pan: PidGains,
+    pub tilt: PidGains,
+    /// Смещение меньше этого угла не исправляется
+    pub deadband_deg: f32,
+    pub max_rate_deg_s: f32,
+    /// 0 — без ограничения ускорения
+    pub max_accel_deg_s2: f32,
+    /// Предел интеграла ПИД, градусы·секунды
+    pub integral_limit: f32,
+    pub lost_behavior: LostBehavior,
+    pub center_after_secs: f32,
+    /// Скорость возврата, град/с на градус отклонения
+    pub center_kp: f32,
+    /// Постоянная времени модели привода `sim` и эмулятора SimpleBGC
+    pub sim_lag_secs: f32,
+    /// Порт для `sbgc` и `pelco_d`; `"pty"` — псевдотерминал с эмулятором
+    pub serial: String,
+    pub baud: u32,
+    pub pelco_address: u8,
+    /// Скорость устройства Pelco-D на максимальном шаге 0x3F
+    pub pelco_max_rate_deg_s: f32,
+}
+
+impl Default for GimbalConfig {
+    fn default() -> Self {
+        let gains = PidGains {
+            kp: 2.0,
+            ki: 0.2,
+            kd: 0.05,
+        };
+        Self {
+            enabled: false,
+            actuator: ActuatorKind::Sim,
+            pan: gains,
+            tilt: gains,
+            deadband_deg: 0.5,
+            max_rate_deg_s: 90.0,
+            max_accel_deg_s2: 360.0,
+            integral_limit: 20.0,
+            lost_behavior: LostBehavior::Hold,
+            center_after_secs: 2.0,
+            center_kp: 1.0,
+            sim_lag_secs: 0.05,
+            serial: "/dev/ttyS1".to_string(),
+            baud: 115200,
+            pelco_address: 1,
+            pelco_max_rate_deg_s: 60.0,
+        }
+    }
+}
+
+/// Запись сырого входа и покадровой разметки для воспроизведения.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub enabled: bool,
+    pub directory: String,
+    /// Элементы GStreamer после `videoconvert`
+    pub encoder: String,
+    pub muxer: String,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "recordings".to_string(),
+            encoder: "x264enc tune=zerolatency speed-preset=ultrafast".to_string(),
+            muxer: "matroskamux".to_string(),
+        }
+    }
+}
+
+/// Путь к модели: абсолютный используется как есть, относительный ищется в `models/`.
+pub fn model_path(name: &str) -> PathBuf {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("models")
+            .join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_can_be_disabled() {
+        let parse =
+            |text: &str| toml::from_str::<TrackersConfig>(text).map(|config| config.fallback);
+        assert_eq!(parse("").unwrap(), Some(TrackerKind::Vit));
+        assert_eq!(
+            parse("fallback = \"dasiamrpn\"").unwrap(),
+            Some(TrackerKind::Dasiamrpn)
+        );
+        assert_eq!(parse("fallback = \"none\"").unwrap(), None);
+        assert!(parse("fallback = \"nothing\"").is_err());
+    }
+}
@@ -0,0 +1,586 @@
+use crate::config::TrackerKind;
+use crate::trackers::TrackResult;
+use crate::utils::BBox;
+use log::{debug, info, warn};
+use opencv::core::Rect;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value, json};
+use std::io::{self, BufRead, BufReader, Write as _};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Команда вида `{"cmd": "lock", "bbox": [x, y, w, h]}`; в JSON разбирается через [`RawCommand`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawCommand", into = "RawCommand")]
+pub enum Command {
+    Lock(Rect),
+    /// Номер детекции из последнего `status`
+    LockDetection(usize),
+    Release,
+    /// Сбросить трекер и найти цель детектором заново
+    Redetect,
+    SwitchTracker {
+        primary: TrackerKind,
+        /// `None` — не менять, `Some(None)` — без запасного
+        fallback: Option<Option<TrackerKind>>,
+    },
+    SetThresholds {
+        primary: Option<f32>,
+        fallback: Option<f32>,
+    },
+    Status,
+}
+
+/// Команда в том виде, в котором её присылает клиент.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum RawCommand {
+    Lock {
+        bbox: [i32; 4],
+    },
+    LockDetection {
+        id: usize,
+    },
+    Release,
+    Redetect,
+    SwitchTracker {
+        primary: TrackerKind,
+        #[serde(
+            default,
+            deserialize_with = "present",
+            skip_serializing_if = "Option::is_none"
+        )]
+        fallback: Option<Option<TrackerKind>>,
+    },
+    SetThresholds {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        primary: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fallback: Option<f32>,
+    },
+    Status,
+}
+
+/// Отличает `"fallback": null` (без запасного) от отсутствующего поля.
+fn present<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Option<TrackerKind>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+fn threshold(value: Option<f32>) -> Result<Option<f32>, String> {
+    match value {
+        Some(v) if !(0.0..=1.0).contains(&v) => {
+            Err("Threshold must be a number in 0..1".to_string())
+        }
+        value => Ok(value),
+    }
+}
+
+impl TryFrom<RawCommand> for Command {
+    type Error = String;
+
+    fn try_from(raw: RawCommand) -> Result<Self, String> {
+        match raw {
+            RawCommand::Lock { bbox: [x, y, w, h] } if w > 0 && h > 0 => {
+                Ok(Command::Lock(Rect::new(x, y, w, h)))
+            }
+            RawCommand::Lock { .. } => Err("\"bbox\" must be [x, y, width, height]".to_string()),
+            RawCommand::LockDetection { id } => Ok(Command::LockDetection(id)),
+            RawCommand::Release => Ok(Command::Release),
+            RawCommand::Redetect => Ok(Command::Redetect),
+            RawCommand::SwitchTracker { primary, fallback } => {
+                Ok(Command::SwitchTracker { primary, fallback })
+            }
+            RawCommand::SetThresholds { primary, fallback } => Ok(Command::SetThresholds {
+                primary: threshold(primary)?,
+                fallback: threshold(fallback)?,
+            }),
+            RawCommand::Status => Ok(Command::Status),
+        }
+    }
+}
+
+impl From<Command> for RawCommand {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::Lock(rect) => RawCommand::Lock {
+                bbox: [rect.x, rect.y, rect.width, rect.height],
+            },
+            Command::LockDetection(id) => RawCommand::LockDetection { id },
+            Command::Release => RawCommand::Release,
+            Command::Redetect => RawCommand::Redetect,
+            Command::SwitchTracker { primary, fallback } => {
+                RawCommand::SwitchTracker { primary, fallback }
+            }
+            Command::SetThresholds { primary, fallback } => {
+                RawCommand::SetThresholds { primary, fallback }
+            }
+            Command::Status => RawCommand::Status,
+        }
+    }
+}
+
+fn bbox_json(rect: Rect) -> Value {
+    json!([rect.x, rect.y, rect.width, rect.height])
+}
+
+/// Откуда взялась цель.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockOrigin {
+    Detector,
+    Operator,
+    Api,
+}
+
+impl LockOrigin {
+    pub fn name(self) -> &'static str {
+        match self {
+            LockOrigin::Detector => "detector",
+            LockOrigin::Operator => "operator",
+            LockOrigin::Api => "api",
+        }
+    }
+}
+
+/// Асинхронные события для подписчиков.
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    Locked {
+        track_id: u64,
+        bbox: Rect,
+        origin: LockOrigin,
+    },
+    Lost {
+        track_id: u64,
+    },
+    Released {
+        origin: LockOrigin,
+    },
+    TrackerSwitched {
+        primary: TrackerKind,
+        fallback: Option<TrackerKind>,
+    },
+    /// Результат трекера на каждом кадре; только для подписок с `"frames": true`
+    Track {
+        track_id: u64,
+        result: TrackResult,
+    },
+}
+
+impl Event {
+    pub fn to_json(self) -> Value {
+        match self {
+            Event::Locked {
+                track_id,
+                bbox,
+                origin,
+            } => json!({
+                "event": "locked",
+                "track_id": track_id,
+                "bbox": bbox_json(bbox),
+                "origin": origin,
+            }),
+            Event::Lost { track_id } => json!({ "event": "lost", "track_id": track_id }),
+            Event::Released { origin } => json!({ "event": "released", "origin": origin }),
+            Event::TrackerSwitched { primary, fallback } => json!({
+                "event": "tracker_switched",
+                "primary": primary,
+                "fallback": fallback,
+            }),
+            Event::Track { track_id, result } => json!({
+                "event": "track",
+                "track_id": track_id,
+                "bbox": bbox_json(result.bbox),
+                "score": result.score,
+                "source": result.source,
+                "inference_ms": result.inference_time.as_secs_f64() * 1000.0,
+            }),
+        }
+    }
+
+    fn is_frame(&self) -> bool {
+        matches!(self, Event::Track { .. })
+    }
+}
+
+/// Снимок состояния для ответа на `status`.
+pub struct Status<'a> {
+    pub track_id: Option<u64>,
+    pub target: Option<&'a TrackResult>,
+    pub primary: TrackerKind,
+    pub fallback: Option<TrackerKind>,
+    pub primary_threshold: f32,
+    pub fallback_threshold: f32,
+    pub thermal_level: usize,
+    pub detections: &'a [BBox],
+}
+
+impl Status<'_> {
+    pub fn to_json(&self) -> Value {
+        let mut json = json!({
+            "tracking": self.track_id.is_some(),
+            "primary": self.primary,
+            "fallback": self.fallback,
+            "primary_threshold": self.primary_threshold,
+            "fallback_threshold": self.fallback_threshold,
+            "thermal_level": self.thermal_level,
+            "detections": self
+                .detections
+                .iter()
+                .enumerate()
+                .map(|(id, bbox)| {
+                    json!({
+                        "id": id,
+                        "bbox": bbox_json(bbox.rect()),
+                        "class_id": bbox.class_id,
+                        "confidence": bbox.confidence,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        });
+        if let (Some(track_id), Some(target)) = (self.track_id, self.target) {
+            json["track_id"] = json!(track_id);
+            json["bbox"] = bbox_json(target.bbox);
+            json["score"] = json!(target.score);
+            json["source"] = json!(target.source);
+        }
+        json
+    }
+}
+
+/// Команда от клиента; ответ отправляется через [`Request::ok`] или [`Request::error`].
+pub struct Request {
+    pub command: Command,
+    /// Поле `id` запроса, возвращается в ответе как есть
+    request_id: Option<Value>,
+    reply: Sender<String>,
+}
+
+fn reply_json(request_id: &Option<Value>, mut body: Map<String, Value>) -> String {
+    if let Some(id) = request_id {
+        body.insert("id".to_string(), id.clone());
+    }
+    Value::Object(body).to_string()
+}
+
+fn ok_body(fields: &Value) -> Map<String, Value> {
+    let mut body = Map::new();
+    body.insert("ok".to_string(), Value::Bool(true));
+    if let Value::Object(fields) = fields {
+        body.extend(fields.clone());
+    }
+    body
+}
+
+fn error_body(message: &str) -> Map<String, Value> {
+    let mut body = Map::new();
+    body.insert("ok".to_string(), Value::Bool(false));
+    body.insert("error".to_string(), Value::String(message.to_string()));
+    body
+}
+
+fn send_line(stream: &mut UnixStream, line: &str) -> std::io::Result<()> {
+    // Одна запись на строку, чтобы ответы не перемешивались с событиями
+    stream.write_all(format!("{}\n", line).as_bytes())
+}
+
+impl Request {
+    fn respond(&self, body: Map<String, Value>) {
+        let _ = self.reply.send(reply_json(&self.request_id, body));
+    }
+
+    /// `fields` — объект с дополнительными полями ответа или `null`.
+    pub fn ok(&self, fields: &Value) {
+        self.respond(ok_body(fields));
+    }
+
+    pub fn error(&self, message: &str) {
+        self.respond(error_body(message));
+    }
+}
+
+struct Subscriber {
+    stream: UnixStream,
+    frames: bool,
+}
+
+/// Сервер управления на Unix-сокете: по строке JSON на команду и на ответ.
+///
+/// Команды выполняет поток обработки кадров через [`ControlServer::poll`];
+/// `{"cmd": "subscribe"}` переводит соединение в режим получения событий.
+pub struct ControlServer {
+    rx: Receiver<Request>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl ControlServer {
+    pub fn start(path: &str) -> std::io::Result<Self> {
+        // Сокет от прошлого запуска мешает bind; любой другой файл по этому пути не трогаем
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path),
+                ));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        let listener = UnixListener::bind(path)?;
+        info!("Control socket {}", path);
+
+        let (tx, rx) = mpsc::channel();
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+
+        let shared = subscribers.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let tx = tx.clone();
+                        let subscribers = shared.clone();
+                        std::thread::spawn(move || {
+                            if let Err(err) = handle_client(stream, tx, subscribers) {
+                                debug!("Control client disconnected: {}", err);
+                            }
+                        });
+                    }
+                    Err(err) => warn!("Control connection failed: {}", err),
+                }
+            }
+        });
+
+        Ok(Self { rx, subscribers })
+    }
+
+    pub fn poll(&self) -> Vec<Request> {
+        self.rx.try_iter().collect()
+    }
+
+    pub fn has_frame_subscribers(&self) -> bool {
+        self.subscribers.lock().unwrap().iter().any(|s| s.frames)
+    }
+
+    /// Рассылает событие; отвалившиеся и не успевающие читать подписчики удаляются.
+    pub fn publish(&self, event: &Event) {
+        let line = event.to_json().to_string() + "\n";
+        let frame = event.is_frame();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain_mut(|s| (frame && !s.frames) || s.stream.write_all(line.as_bytes()).is_ok());
+    }
+}
+
+fn handle_client(
+    stream: UnixStream,
+    tx: Sender<Request>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream.try_clone()?);
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Вложенность ограничена разборщиком, так что глубокий JSON не уронит поток
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                send_line(
+                    &mut writer,
+                    &reply_json(&None, error_body(&err.to_string())),
+                )?;
+                continue;
+            }
+        };
+        let request_id = request.get("id").cloned();
+
+        if request.get("cmd").and_then(Value::as_str) == Some("subscribe") {
+            let frames = request
+                .get("frames")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            let subscriber = stream.try_clone()?;
+            // Медленный подписчик не должен тормозить обработку кадров
+            subscriber.set_write_timeout(Some(Duration::from_millis(100)))?;
+            // Подтверждение пишется до регистрации, чтобы прийти раньше событий
+            send_line(&mut writer, &reply_json(&request_id, ok_body(&Value::Null)))?;
+            subscribers.lock().unwrap().push(Subscriber {
+                stream: subscriber,
+                frames,
+            });
+            continue;
+        }
+
+        let command = match Command::deserialize(&request) {
+            Ok(command) => command,
+            Err(err) => {
+                send_line(
+                    &mut writer,
+                    &reply_json(&request_id, error_body(&err.to_string())),
+                )?;
+                continue;
+            }
+        };
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let request_id_copy = request_id.clone();
+        if tx
+            .send(Request {
+                command,
+                request_id,
+                reply: reply_tx,
+            })
+            .is_err()
+        {
+            return Ok(());
+        }
+        // Поток обработки отвечает на следующем кадре
+        let reply = reply_rx
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap_or_else(|_| reply_json(&request_id_copy, error_body("Timeout")));
+        send_line(&mut writer, &reply)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, String> {
+        serde_json::from_str(line).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn parses_client_commands() {
+        assert_eq!(
+            parse(r#"{"cmd": "lock", "bbox": [10, 20, 30, 40], "id": "a"}"#),
+            Ok(Command::Lock(Rect::new(10, 20, 30, 40)))
+        );
+        assert_eq!(
+            parse(r#"{"cmd": "lock_detection", "id": 2}"#),
+            Ok(Command::LockDetection(2))
+        );
+        assert_eq!(parse(r#"{"cmd": "release"}"#), Ok(Command::Release));
+        assert_eq!(
+            parse(r#"{"cmd": "switch_tracker", "primary": "kcf"}"#),
+            Ok(Command::SwitchTracker {
+                primary: TrackerKind::Kcf,
+                fallback: None
+            })
+        );
+        assert_eq!(
+            parse(r#"{"cmd": "switch_tracker", "primary": "nano", "fallback": null}"#),
+            Ok(Command::SwitchTracker {
+                primary: TrackerKind::Nano,
+                fallback: Some(None)
+            })
+        );
+        assert_eq!(
+            parse(r#"{"cmd": "set_thresholds", "fallback": 0.25}"#),
+            Ok(Command::SetThresholds {
+                primary: None,
+                fallback: Some(0.25)
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_commands() {
+        for line in [
+            r#"{"cmd": "lock", "bbox": [10, 20, 0, 40]}"#,
+            r#"{"cmd": "lock", "bbox": [10, 20, 30]}"#,
+            r#"{"cmd": "lock_detection", "id": -1}"#,
+            r#"{"cmd": "switch_tracker"}"#,
+            r#"{"cmd": "switch_tracker", "primary": "mosse"}"#,
+            r#"{"cmd": "set_thresholds", "primary": 1.5}"#,
+            r#"{"cmd": "fly"}"#,
+            r#"{"bbox": [1, 2, 3, 4]}"#,
+        ] {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        let commands = [
+            Command::Lock(Rect::new(1, 2, 3, 4)),
+            Command::LockDetection(7),
+            Command::Release,
+            Command::Redetect,
+            Command::SwitchTracker {
+                primary: TrackerKind::VitInt8,
+                fallback: None,
+            },
+            Command::SwitchTracker {
+                primary: TrackerKind::Vit,
+                fallback: Some(None),
+            },
+            Command::SwitchTracker {
+                primary: TrackerKind::Nano,
+                fallback: Some(Some(TrackerKind::Kcf)),
+            },
+            Command::SetThresholds {
+                primary: Some(0.5),
+                fallback: None,
+            },
+            Command::Status,
+        ];
+        for command in commands {
+            let line = serde_json::to_string(&command).unwrap();
+            assert_eq!(parse(&line), Ok(command), "{}", line);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let line = "[".repeat(100_000) + &"]".repeat(100_000);
+        assert!(serde_json::from_str::<Value>(&line).is_err());
+    }
+
+    #[test]
+    fn replies_echo_request_id() {
+        let reply: Value = serde_json::from_str(&reply_json(
+            &Some(json!("a")),
+            ok_body(&json!({ "track_id": 3 })),
+        ))
+        .unwrap();
+        assert_eq!(reply, json!({ "id": "a", "ok": true, "track_id": 3 }));
+        let reply: Value =
+            serde_json::from_str(&reply_json(&None, error_body("No \"such\" detection"))).unwrap();
+        assert_eq!(
+            reply,
+            json!({ "ok": false, "error": "No \"such\" detection" })
+        );
+    }
+
+    #[test]
+    fn start_replaces_only_stale_sockets() {
+        let dir = std::env::temp_dir().join(format!("control-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join("not-a-socket");
+        std::fs::write(&file, "keep").unwrap();
+        let err = ControlServer::start(file.to_str().unwrap())
+            .err()
+            .expect("Regular file must not be replaced");
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep");
+
+        let socket = dir.join("stale.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(ControlServer::start(socket.to_str().unwrap()).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,685 @@
+//! Оценка детектора по разметке COCO:
+//! `eval-detector <images> <instances.json> [--preprocess stretch|letterbox]... [--nms <iou>|none]... [--limit <n>] [--out <prefix>]`
+//!
+//! Каждое сочетание предобработки и NMS — отдельный вариант. Сеть прогоняется один раз
+//! на предобработку, NMS применяется к сохранённым кандидатам. Для каждого класса
+//! считаются AP@0.5 и AP@0.5:0.95 по 101 точке полноты, как в pycocotools, кривая
+//! точность/полнота при IoU 0.5 и точка на ней при `confidence_threshold` из конфига.
+//! Отчёт — `<prefix>.json` и `<prefix>.csv` по классам, сводная таблица — в stdout.
+
+use crate::config::{DetectorConfig, Preprocess};
+use crate::utils::BBox;
+use crate::yolo::{COCO_CLASSES, YoloV8, nms};
+use log::{info, warn};
+use opencv::imgcodecs;
+use opencv::prelude::*;
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "Usage: eval-detector <images> <instances.json> [--preprocess stretch|letterbox]... [--nms <iou>|none]... [--limit <n>] [--out <prefix>]";
+
+/// Порог уверенности для сбора кандидатов, как в стандартной оценке COCO
+const MIN_SCORE: f32 = 0.001;
+/// Детекций на изображение, не больше
+const MAX_DETECTIONS: usize = 100;
+/// Точек полноты в кривой точность/полнота
+const RECALL_POINTS: usize = 101;
+
+struct GroundTruth {
+    bbox: BBox,
+    crowd: bool,
+}
+
+struct Image {
+    path: PathBuf,
+    groundtruth: Vec<GroundTruth>,
+}
+
+/// Разметка COCO: нужные для оценки поля `instances_*.json`.
+#[derive(Deserialize)]
+struct Coco {
+    images: Vec<CocoImage>,
+    annotations: Vec<CocoAnnotation>,
+    categories: Vec<CocoCategory>,
+}
+
+#[derive(Deserialize)]
+struct CocoImage {
+    id: u64,
+    file_name: String,
+}
+
+#[derive(Deserialize)]
+struct CocoAnnotation {
+    image_id: u64,
+    category_id: u64,
+    /// `[x, y, ширина, высота]`
+    bbox: [f32; 4],
+    #[serde(default)]
+    iscrowd: u8,
+}
+
+#[derive(Deserialize)]
+struct CocoCategory {
+    id: u64,
+    name: String,
+}
+
+/// Изображения и рамки; категории COCO сопоставляются классам модели по имени.
+fn load_coco(images_dir: &Path, annotations: &Path) -> Result<Vec<Image>, String> {
+    let text = fs::read_to_string(annotations)
+        .map_err(|err| format!("{}: {}", annotations.display(), err))?;
+    let coco: Coco =
+        serde_json::from_str(&text).map_err(|err| format!("{}: {}", annotations.display(), err))?;
+
+    let mut classes: HashMap<u64, usize> = HashMap::new();
+    for category in &coco.categories {
+        match COCO_CLASSES
+            .iter()
+            .position(|&class| class == category.name)
+        {
+            Some(class) => {
+                classes.insert(category.id, class);
+            }
+            None => warn!("Category '{}' is not a model class, ignored", category.name),
+        }
+    }
+
+    let mut index: HashMap<u64, usize> = HashMap::new();
+    let mut images: Vec<Image> = Vec::with_capacity(coco.images.len());
+    for image in coco.images {
+        index.insert(image.id, images.len());
+        images.push(Image {
+            path: images_dir.join(image.file_name),
+            groundtruth: Vec::new(),
+        });
+    }
+
+    for annotation in coco.annotations {
+        let (Some(&image), Some(&class)) = (
+            index.get(&annotation.image_id),
+            classes.get(&annotation.category_id),
+        ) else {
+            continue;
+        };
+        let [x, y, w, h] = annotation.bbox;
+        images[image].groundtruth.push(GroundTruth {
+            bbox: BBox {
+                x1: x,
+                y1: y,
+                x2: x + w,
+                y2: y + h,
+                class_id: class,
+                confidence: 1.0,
+            },
+            crowd: annotation.iscrowd != 0,
+        });
+    }
+    Ok(images)
+}
+
+/// Перекрытие с «толпой» — доля площади детекции, как в pycocotools.
+fn crowd_overlap(detection: &BBox, crowd: &BBox) -> f32 {
+    let w = (detection.x2.min(crowd.x2) - detection.x1.max(crowd.x1)).max(0.0);
+    let h = (detection.y2.min(crowd.y2) - detection.y1.max(crowd.y1)).max(0.0);
+    let area = (detection.x2 - detection.x1) * (detection.y2 - detection.y1);
+    if area > 0.0 { w * h / area } else { 0.0 }
+}
+
+/// Сопоставление детекций одного класса с разметкой при пороге IoU.
+struct Matching {
+    /// Уверенность и попадание для учтённых детекций по убыванию уверенности
+    detections: Vec<(f32, bool)>,
+    positives: usize,
+}
+
+fn match_class(
+    images: &[Image],
+    detections: &[Vec<BBox>],
+    class: usize,
+    threshold: f32,
+) -> Matching {
+    let mut scored: Vec<(f32, usize, &BBox)> = detections
+        .iter()
+        .enumerate()
+        .flat_map(|(image, boxes)| {
+            boxes
+                .iter()
+                .filter(|b| b.class_id == class)
+                .map(move |b| (b.confidence, image, b))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut matched: Vec<Vec<bool>> = images
+        .iter()
+        .map(|image| vec![false; image.groundtruth.len()])
+        .collect();
+    let positives = images
+        .iter()
+        .flat_map(|image| &image.groundtruth)
+        .filter(|gt| gt.bbox.class_id == class && !gt.crowd)
+        .count();
+
+    let mut result = Vec::with_capacity(scored.len());
+    for (score, image, detection) in scored {
+        let groundtruth = &images[image].groundtruth;
+        let best = groundtruth
+            .iter()
+            .enumerate()
+            .filter(|(i, gt)| gt.bbox.class_id == class && !gt.crowd && !matched[image][*i])
+            .map(|(i, gt)| (i, detection.iou(&gt.bbox)))
+            .filter(|&(_, iou)| iou >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((i, _)) => {
+                matched[image][i] = true;
+                result.push((score, true));
+            }
+            // Попадание в «толпу» не считается ни верным, ни ложным
+            None if groundtruth.iter().any(|gt| {
+                gt.bbox.class_id == class
+                    && gt.crowd
+                    && crowd_overlap(detection, &gt.bbox) >= threshold
+            }) => {}
+            None => result.push((score, false)),
+        }
+    }
+    Matching {
+        detections: result,
+        positives,
+    }
+}
+
+impl Matching {
+    /// Точность на 101 точке полноты 0..1 с огибающей справа, как в pycocotools.
+    fn precision_curve(&self) -> Vec<f32> {
+        let mut precision = Vec::with_capacity(self.detections.len());
+        let mut recall = Vec::with_capacity(self.detections.len());
+        let mut tp = 0;
+        for (i, &(_, hit)) in self.detections.iter().enumerate() {
+            tp += hit as usize;
+            precision.push(tp as f32 / (i + 1) as f32);
+            recall.push(tp as f32 / self.positives.max(1) as f32);
+        }
+        for i in (1..precision.len()).rev() {
+            precision[i - 1] = precision[i - 1].max(precision[i]);
+        }
+        (0..RECALL_POINTS)
+            .map(|r| {
+                let r = r as f32 / (RECALL_POINTS - 1) as f32;
+                recall
+                    .iter()
+                    .position(|&recall| recall >= r)
+                    .map_or(0.0, |i| precision[i])
+            })
+            .collect()
+    }
+
+    /// Верные срабатывания и всего детекций с уверенностью не ниже порога.
+    fn at_threshold(&self, threshold: f32) -> (usize, usize) {
+        let kept = self
+            .detections
+            .iter()
+            .take_while(|(score, _)| *score >= threshold);
+        kept.fold((0, 0), |(tp, total), &(_, hit)| {
+            (tp + hit as usize, total + 1)
+        })
+    }
+}
+
+struct ClassReport {
+    class: usize,
+    positives: usize,
+    ap50: f32,
+    ap50_95: f32,
+    curve: Vec<f32>,
+    true_positives: usize,
+    detections: usize,
+}
+
+struct VariantReport {
+    name: String,
+    preprocess: Preprocess,
+    nms: Option<f32>,
+    time_per_image: Duration,
+    classes: Vec<ClassReport>,
+}
+
+fn ratio(a: usize, b: usize) -> f32 {
+    if b > 0 { a as f32 / b as f32 } else { 0.0 }
+}
+
+impl VariantReport {
+    fn mean_ap(&self, ap: fn(&ClassReport) -> f32) -> f32 {
+        self.classes.iter().map(ap).sum::<f32>() / self.classes.len().max(1) as f32
+    }
+
+    /// Точность и полнота по всем классам при пороге из конфига.
+    fn precision_recall(&self) -> (f32, f32) {
+        let sum = |f: fn(&ClassReport) -> usize| self.classes.iter().map(f).sum::<usize>();
+        let tp = sum(|c| c.true_positives);
+        (
+            ratio(tp, sum(|c| c.detections)),
+            ratio(tp, sum(|c| c.positives)),
+        )
+    }
+}
+
+/// Метрики по классам, у которых есть разметка; AP@0.5:0.95 — среднее по порогам 0.5, 0.55, ..., 0.95.
+fn evaluate(
+    images: &[Image],
+    detections: &[Vec<BBox>],
+    confidence_threshold: f32,
+) -> Vec<ClassReport> {
+    let mut reports = Vec::new();
+    for class in 0..COCO_CLASSES.len() {
+        let matchings: Vec<Matching> = (0..10)
+            .map(|i| match_class(images, detections, class, 0.5 + 0.05 * i as f32))
+            .collect();
+        if matchings[0].positives == 0 {
+            continue;
+        }
+        let ap = |m: &Matching| m.precision_curve().iter().sum::<f32>() / RECALL_POINTS as f32;
+        let (true_positives, total) = matchings[0].at_threshold(confidence_threshold);
+        reports.push(ClassReport {
+            class,
+            positives: matchings[0].positives,
+            ap50: ap(&matchings[0]),
+            ap50_95: matchings.iter().map(ap).sum::<f32>() / matchings.len() as f32,
+            curve: matchings[0].precision_curve(),
+            true_positives,
+            detections: total,
+        });
+    }
+    reports
+}
+
+fn preprocess_name(preprocess: Preprocess) -> &'static str {
+    match preprocess {
+        Preprocess::Stretch => "stretch",
+        Preprocess::Letterbox => "letterbox",
+    }
+}
+
+fn variant_name(preprocess: Preprocess, nms: Option<f32>) -> String {
+    match nms {
+        Some(iou) => format!("{}/nms{}", preprocess_name(preprocess), iou),
+        None => format!("{}/no-nms", preprocess_name(preprocess)),
+    }
+}
+
+fn write_reports(
+    prefix: &str,
+    annotations: &Path,
+    images: usize,
+    confidence_threshold: f32,
+    variants: &[VariantReport],
+) -> std::io::Result<()> {
+    let mut csv = String::from("variant,class,gt,ap50,ap50_95,precision,recall\n");
+    let mut reports = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let mut classes = Vec::with_capacity(variant.classes.len());
+        for class in &variant.classes {
+            let (precision, recall) = (
+                ratio(class.true_positives, class.detections),
+                ratio(class.true_positives, class.positives),
+            );
+            let name = COCO_CLASSES[class.class];
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.4},{:.4},{:.4},{:.4}",
+                variant.name, name, class.positives, class.ap50, class.ap50_95, precision, recall
+            );
+            classes.push(json!({
+                "name": name,
+                "gt": class.positives,
+                "ap50": class.ap50,
+                "ap50_95": class.ap50_95,
+                "precision": precision,
+                "recall": recall,
+                "pr_curve_iou50": class.curve,
+            }));
+        }
+        let (precision, recall) = variant.precision_recall();
+        reports.push(json!({
+            "name": variant.name,
+            "preprocess": preprocess_name(variant.preprocess),
+            "nms_iou_threshold": variant.nms,
+            "ms_per_image": variant.time_per_image.as_secs_f64() * 1000.0,
+            "map50": variant.mean_ap(|c| c.ap50),
+            "map50_95": variant.mean_ap(|c| c.ap50_95),
+            "precision": precision,
+            "recall": recall,
+            "classes": classes,
+        }));
+    }
+    let json = json!({
+        "annotations": annotations.display().to_string(),
+        "images": images,
+        "confidence_threshold": confidence_threshold,
+        "variants": reports,
+    });
+
+    fs::write(format!("{}.json", prefix), json.to_string() + "\n")?;
+    fs::write(format!("{}.csv", prefix), csv)?;
+    Ok(())
+}
+
+fn print_table(variants: &[VariantReport], confidence_threshold: f32) {
+    println!(
+        "{:<22} {:>8} {:>10} {:>8} {:>8} {:>8}",
+        "variant",
+        "mAP50",
+        "mAP50:95",
+        format!("P@{}", confidence_threshold),
+        format!("R@{}", confidence_threshold),
+        "ms"
+    );
+    for variant in variants {
+        let (precision, recall) = variant.precision_recall();
+        println!(
+            "{:<22} {:>8.3} {:>10.3} {:>8.3} {:>8.3} {:>8.1}",
+            variant.name,
+            variant.mean_ap(|c| c.ap50),
+            variant.mean_ap(|c| c.ap50_95),
+            precision,
+            recall,
+            variant.time_per_image.as_secs_f64() * 1000.0
+        );
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
+
+/// Подкоманда `eval-detector`; `args` — аргументы после её имени.
+pub fn main(config: &DetectorConfig, args: &[String]) -> opencv::Result<()> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut preprocesses: Vec<Preprocess> = Vec::new();
+    let mut nms_thresholds: Vec<Option<f32>> = Vec::new();
+    let mut limit: Option<usize> = None;
+    let mut prefix: Option<String> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .unwrap_or_else(|| usage_error(&format!("Missing value for {}", arg)))
+        };
+        match arg.as_str() {
+            "--preprocess" => preprocesses.push(
+                Preprocess::deserialize(value().as_str().into_deserializer())
+                    .unwrap_or_else(|err: serde::de::value::Error| usage_error(&err.to_string())),
+            ),
+            "--nms" => nms_thresholds.push(match value().as_str() {
+                "none" => None,
+                iou => Some(
+                    iou.parse()
+                        .unwrap_or_else(|_| usage_error(&format!("Bad NMS threshold {}", iou))),
+                ),
+            }),
+            "--limit" => {
+                limit = Some(
+                    value()
+                        .parse()
+                        .unwrap_or_else(|_| usage_error("Bad --limit")),
+                )
+            }
+            "--out" => prefix = Some(value()),
+            _ if paths.len() < 2 && !arg.starts_with("--") => paths.push(PathBuf::from(arg)),
+            _ => usage_error(&format!("Unknown argument {}", arg)),
+        }
+    }
+    let [images_dir, annotations] = &paths[..] else {
+        usage_error("Missing images directory or annotations");
+    };
+    if preprocesses.is_empty() {
+        preprocesses.push(config.preprocess);
+    }
+    if nms_thresholds.is_empty() {
+        nms_thresholds.push(config.nms_iou_threshold);
+    }
+    let prefix = prefix.unwrap_or_else(|| {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_secs());
+        format!("eval-detector-{}", stamp)
+    });
+
+    let mut images = load_coco(images_dir, annotations).unwrap_or_else(|err| usage_error(&err));
+    images.retain(|image| {
+        let exists = image.path.is_file();
+        if !exists {
+            warn!("Skip missing {}", image.path.display());
+        }
+        exists
+    });
+    if let Some(limit) = limit {
+        images.truncate(limit);
+    }
+    info!(
+        "Evaluating detector on {} images from {}",
+        images.len(),
+        annotations.display()
+    );
+
+    let mut yolo = YoloV8::new(config).expect("Can't load detector");
+    // Кандидаты собираются с низким порогом, порог из конфига — лишь точка на кривой
+    yolo.confidence_threshold = MIN_SCORE.min(config.confidence_threshold);
+    yolo.nms_iou_threshold = None;
+    let input_size = match yolo.input_size {
+        Some(size) if size != config.input_size => {
+            warn!(
+                "Detector model has static input {}, ignore input_size = {}",
+                size, config.input_size
+            );
+            size
+        }
+        _ => config.input_size,
+    };
+
+    let mut variants = Vec::new();
+    for &preprocess in &preprocesses {
+        yolo.preprocess = preprocess;
+        let mut elapsed = Duration::ZERO;
+        let mut candidates = Vec::with_capacity(images.len());
+        for (i, image) in images.iter().enumerate() {
+            let frame = imgcodecs::imread(&image.path.to_string_lossy(), imgcodecs::IMREAD_COLOR)?;
+            if frame.empty() {
+                warn!("Can't read {}", image.path.display());
+                candidates.push(Vec::new());
+                continue;
+            }
+            let start = Instant::now();
+            let (input, transform) = yolo.prepare(&frame, input_size);
+            match yolo.inference(&input) {
+                Ok(output) => candidates.push(yolo.decode(&output, &transform)),
+                Err(err) => {
+                    warn!("Inference failed on {}: {}", image.path.display(), err);
+                    candidates.push(Vec::new());
+                }
+            }
+            elapsed += start.elapsed();
+            if (i + 1) % 500 == 0 {
+                info!(
+                    "{}: {} / {} images",
+                    preprocess_name(preprocess),
+                    i + 1,
+                    images.len()
+                );
+            }
+        }
+
+        for &nms_threshold in &nms_thresholds {
+            let detections: Vec<Vec<BBox>> = candidates
+                .iter()
+                .map(|boxes| {
+                    let mut boxes = match nms_threshold {
+                        Some(iou) => nms(boxes.clone(), iou),
+                        None => boxes.clone(),
+                    };
+                    boxes.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+                    boxes.truncate(MAX_DETECTIONS);
+                    boxes
+                })
+                .collect();
+            variants.push(VariantReport {
+                name: variant_name(preprocess, nms_threshold),
+                preprocess,
+                nms: nms_threshold,
+                time_per_image: elapsed / images.len().max(1) as u32,
+                classes: evaluate(&images, &detections, config.confidence_threshold),
+            });
+        }
+    }
+
+    print_table(&variants, config.confidence_threshold);
+    match write_reports(
+        &prefix,
+        annotations,
+        images.len(),
+        config.confidence_threshold,
+        &variants,
+    ) {
+        Ok(()) => info!("Report written to {}.json and {}.csv", prefix, prefix),
+        Err(err) => warn!("Can't write report {}: {}", prefix, err),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERSON: usize = 0;
+    const CAR: usize = 2;
+
+    fn bbox(class_id: usize, x1: f32, y1: f32, x2: f32, y2: f32, confidence: f32) -> BBox {
+        BBox {
+            x1,
+            y1,
+            x2,
+            y2,
+            class_id,
+            confidence,
+        }
+    }
+
+    fn truth(class: usize, x1: f32, y1: f32, x2: f32, y2: f32, crowd: bool) -> GroundTruth {
+        GroundTruth {
+            bbox: bbox(class, x1, y1, x2, y2, 1.0),
+            crowd,
+        }
+    }
+
+    /// Два изображения: на первом два человека, машина и «толпа», на втором один человек.
+    fn dataset() -> (Vec<Image>, Vec<Vec<BBox>>) {
+        let images = vec![
+            Image {
+                path: PathBuf::from("a.jpg"),
+                groundtruth: vec![
+                    truth(PERSON, 0.0, 0.0, 100.0, 100.0, false),
+                    truth(PERSON, 200.0, 0.0, 300.0, 100.0, false),
+                    truth(PERSON, 0.0, 200.0, 400.0, 400.0, true),
+                    truth(CAR, 0.0, 0.0, 50.0, 50.0, false),
+                ],
+            },
+            Image {
+                path: PathBuf::from("b.jpg"),
+                groundtruth: vec![truth(PERSON, 0.0, 0.0, 100.0, 100.0, false)],
+            },
+        ];
+        let detections = vec![
+            vec![
+                bbox(PERSON, 0.0, 0.0, 100.0, 100.0, 0.9),
+                // Дубль уже сопоставленного человека
+                bbox(PERSON, 5.0, 0.0, 105.0, 100.0, 0.8),
+                // Внутри «толпы»
+                bbox(PERSON, 50.0, 250.0, 150.0, 350.0, 0.7),
+                bbox(CAR, 0.0, 0.0, 50.0, 50.0, 0.6),
+            ],
+            vec![
+                // IoU 0.82
+                bbox(PERSON, 10.0, 0.0, 110.0, 100.0, 0.5),
+                bbox(PERSON, 500.0, 500.0, 550.0, 550.0, 0.4),
+            ],
+        ];
+        (images, detections)
+    }
+
+    #[test]
+    fn crowd_overlap_is_fraction_of_detection() {
+        let crowd = bbox(PERSON, 0.0, 200.0, 400.0, 400.0, 1.0);
+        let inside = bbox(PERSON, 50.0, 250.0, 150.0, 350.0, 0.7);
+        assert_eq!(crowd_overlap(&inside, &crowd), 1.0);
+        assert!(inside.iou(&crowd) < 0.5);
+        assert_eq!(
+            crowd_overlap(&bbox(PERSON, 0.0, 150.0, 100.0, 250.0, 0.7), &crowd),
+            0.5
+        );
+        assert_eq!(
+            crowd_overlap(&bbox(PERSON, 0.0, 0.0, 0.0, 10.0, 0.7), &crowd),
+            0.0
+        );
+    }
+
+    #[test]
+    fn matches_and_ignores_crowd() {
+        let (images, detections) = dataset();
+
+        // Детекция в «толпе» выпадает, «толпа» не входит в число объектов
+        let matching = match_class(&images, &detections, PERSON, 0.5);
+        assert_eq!(matching.positives, 3);
+        assert_eq!(
+            matching.detections,
+            [(0.9, true), (0.8, false), (0.5, true), (0.4, false)]
+        );
+
+        let strict = match_class(&images, &detections, PERSON, 0.85);
+        assert_eq!(
+            strict.detections,
+            [(0.9, true), (0.8, false), (0.5, false), (0.4, false)]
+        );
+
+        assert_eq!(matching.at_threshold(0.85), (1, 1));
+        assert_eq!(matching.at_threshold(0.5), (2, 3));
+        assert_eq!(matching.at_threshold(0.0), (2, 4));
+    }
+
+    #[test]
+    fn average_precision_by_hand() {
+        let (images, detections) = dataset();
+
+        // Огибающая точности [1, 2/3, 2/3, 1/2] при полноте [1/3, 1/3, 2/3, 2/3]:
+        // 34 точки полноты до 1/3 дают 1, 33 точки до 2/3 — 2/3, остальные 0
+        let curve = match_class(&images, &detections, PERSON, 0.5).precision_curve();
+        assert_eq!(curve.len(), RECALL_POINTS);
+        assert!(curve[..34].iter().all(|&p| p == 1.0));
+        assert!(curve[34..67].iter().all(|&p| (p - 2.0 / 3.0).abs() < 1e-6));
+        assert!(curve[67..].iter().all(|&p| p == 0.0));
+
+        let reports = evaluate(&images, &detections, 0.5);
+        assert_eq!(
+            reports.iter().map(|r| r.class).collect::<Vec<_>>(),
+            [PERSON, CAR]
+        );
+        let person = &reports[0];
+        assert!((person.ap50 - 56.0 / 101.0).abs() < 1e-6);
+        assert_eq!(
+            (person.positives, person.true_positives, person.detections),
+            (3, 2, 3)
+        );
+        assert!(person.ap50_95 < person.ap50);
+        assert_eq!((reports[1].ap50, reports[1].ap50_95), (1.0, 1.0));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::config::{DnnBackend, DnnConfig, DnnTarget, TrackersConfig};
+use log::{info, warn};
+use opencv::dnn;
+
+impl DnnBackend {
+    pub fn id(self) -> i32 {
+        match self {
+            DnnBackend::Opencv => dnn::DNN_BACKEND_OPENCV,
+            DnnBackend::InferenceEngine => dnn::DNN_BACKEND_INFERENCE_ENGINE,
+            DnnBackend::Timvx => dnn::DNN_BACKEND_TIMVX,
+        }
+    }
+
+    fn as_dnn(self) -> dnn::Backend {
+        match self {
+            DnnBackend::Opencv => dnn::Backend::DNN_BACKEND_OPENCV,
+            DnnBackend::InferenceEngine => dnn::Backend::DNN_BACKEND_INFERENCE_ENGINE,
+            DnnBackend::Timvx => dnn::Backend::DNN_BACKEND_TIMVX,
+        }
+    }
+}
+
+impl DnnTarget {
+    pub fn id(self) -> i32 {
+        match self {
+            DnnTarget::Cpu => dnn::DNN_TARGET_CPU,
+            DnnTarget::CpuFp16 => dnn::DNN_TARGET_CPU_FP16,
+            DnnTarget::Opencl => dnn::DNN_TARGET_OPENCL,
+            DnnTarget::OpenclFp16 => dnn::DNN_TARGET_OPENCL_FP16,
+            DnnTarget::Npu => dnn::DNN_TARGET_NPU,
+        }
+    }
+
+    fn as_dnn(self) -> dnn::Target {
+        match self {
+            DnnTarget::Cpu => dnn::Target::DNN_TARGET_CPU,
+            DnnTarget::CpuFp16 => dnn::Target::DNN_TARGET_CPU_FP16,
+            DnnTarget::Opencl => dnn::Target::DNN_TARGET_OPENCL,
+            DnnTarget::OpenclFp16 => dnn::Target::DNN_TARGET_OPENCL_FP16,
+            DnnTarget::Npu => dnn::Target::DNN_TARGET_NPU,
+        }
+    }
+}
+
+/// Проверяет, что OpenCV собран с нужной парой backend/target,
+/// иначе возвращает OpenCV/CPU.
+pub fn probe(name: &str, requested: DnnConfig) -> DnnConfig {
+    let available = dnn::get_available_targets(requested.backend.as_dnn())
+        .map(|targets| targets.iter().any(|t| t == requested.target.as_dnn()))
+        .unwrap_or(false);
+
+    if available {
+        info!("{} tracker: {:?}/{:?}", name, requested.backend, requested.target);
+        requested
+    } else {
+        warn!(
+            "{} tracker: {:?}/{:?} is not available, fallback to OpenCV/CPU",
+            name, requested.backend, requested.target
+        );
+        DnnConfig::default()
+    }
+}
+
+pub fn probe_trackers(config: &TrackersConfig) -> TrackersConfig {
+    TrackersConfig {
+        nano: probe("NanoTrack", config.nano),
+        vit: probe("ViT", config.vit),
+        dasiamrpn: probe("DaSiamRPN", config.dasiamrpn),
+        ..config.clone()
+    }
+}
@@ -0,0 +1,762 @@
+//! Оценка трекеров на наборах OTB, LaSOT и GOT-10k (протокол OPE: инициализация
+//! по разметке первого кадра, без переинициализации).
+//!
+//! `eval <dataset> [--tracker <primary>[+<fallback>]]... [--sequence <name>]... [--out <prefix>]`
+//!
+//! Формат каждой последовательности определяется по файлам в её каталоге. Для каждой
+//! конфигурации трекеров считаются AUC успеха, точность при 20 px, нормированная
+//! точность и FPS трекера; отчёт пишется в `<prefix>.json`, `<prefix>.csv`
+//! (по последовательностям) и `<prefix>.summary.csv`, сводная таблица — в stdout.
+
+use crate::config::{TrackerKind, TrackersConfig};
+use crate::trackers::{Tracker, create_tracker};
+use crate::utils::iou;
+use log::{info, warn};
+use opencv::core::{Mat, Rect};
+use opencv::imgcodecs;
+use opencv::prelude::*;
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "Usage: eval <dataset> [--tracker <primary>[+<fallback>]]... [--sequence <name>]... [--out <prefix>]";
+
+/// Порог точности по расстоянию между центрами, пиксели
+const PRECISION_PX: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatasetFormat {
+    /// `<seq>/img/*.jpg`, `<seq>/groundtruth_rect[.N].txt`
+    Otb,
+    /// `<class>/<class>-N/img/*.jpg`, `groundtruth.txt`
+    Lasot,
+    /// `<split>/GOT-10k_*/*.jpg`, `groundtruth.txt`, `absence.label`
+    Got10k,
+}
+
+/// Последовательности OTB, у которых разметка начинается не с первого кадра в `img/`.
+const OTB_FIRST_FRAME: &[(&str, usize)] = &[
+    ("David", 300),
+    ("Tiger1", 6),
+    ("BlurCar1", 247),
+    ("BlurCar3", 3),
+    ("BlurCar4", 18),
+];
+
+/// Последовательность с разметкой на каждом кадре. Рамки нулевого размера —
+/// цель отсутствует или не размечена, такие кадры в метриках не учитываются.
+pub struct Sequence {
+    pub name: String,
+    pub format: DatasetFormat,
+    pub frames: Vec<PathBuf>,
+    pub groundtruth: Vec<Rect>,
+}
+
+/// Разделители в разметке разных наборов: запятая, табуляция или пробел.
+pub fn parse_rect(line: &str) -> Option<Rect> {
+    let values: Vec<f32> = line
+        .split([',', '\t', ' '])
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+    match values[..] {
+        [x, y, w, h] => Some(Rect::new(
+            x.round() as i32,
+            y.round() as i32,
+            w.round() as i32,
+            h.round() as i32,
+        )),
+        _ => None,
+    }
+}
+
+fn read_groundtruth(path: &Path) -> Result<Vec<Rect>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            parse_rect(line).ok_or(format!("{}: bad box on line {}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+fn list_images(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut frames: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|err| format!("{}: {}", dir.display(), err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "jpg" || ext == "png")
+        })
+        .collect();
+    frames.sort();
+    Ok(frames)
+}
+
+fn dir_name(dir: &Path) -> String {
+    dir.file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned())
+}
+
+/// Файлы разметки OTB: `groundtruth_rect.txt` или по одному на цель — `groundtruth_rect.N.txt`.
+fn otb_groundtruth_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    let name = dir_name(path);
+                    name.starts_with("groundtruth_rect") && name.ends_with(".txt")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+impl Sequence {
+    /// Формат каталога последовательности, если он на неё похож.
+    pub fn detect(dir: &Path) -> Option<DatasetFormat> {
+        let has_img = dir.join("img").is_dir();
+        if has_img && !otb_groundtruth_files(dir).is_empty() {
+            Some(DatasetFormat::Otb)
+        } else if has_img && dir.join("groundtruth.txt").is_file() {
+            Some(DatasetFormat::Lasot)
+        } else if dir.join("groundtruth.txt").is_file() {
+            Some(DatasetFormat::Got10k)
+        } else {
+            None
+        }
+    }
+
+    /// Загружает последовательности из каталога; в OTB на одну цель — одна последовательность.
+    pub fn load(dir: &Path) -> Result<Vec<Self>, String> {
+        let name = dir_name(dir);
+        match Self::detect(dir) {
+            None => Err(format!("{}: not a sequence", dir.display())),
+            Some(DatasetFormat::Otb) => {
+                let images = list_images(&dir.join("img"))?;
+                let first = OTB_FIRST_FRAME
+                    .iter()
+                    .find(|(seq, _)| *seq == name)
+                    .map_or(1, |&(_, first)| first);
+                otb_groundtruth_files(dir)
+                    .iter()
+                    .map(|path| {
+                        let groundtruth = read_groundtruth(path)?;
+                        let frames = images
+                            .iter()
+                            .skip(first - 1)
+                            .take(groundtruth.len())
+                            .cloned()
+                            .collect();
+                        // groundtruth_rect.2.txt -> Jogging.2
+                        let target = dir_name(path)
+                            .trim_start_matches("groundtruth_rect")
+                            .trim_end_matches(".txt")
+                            .to_string();
+                        Self::new(
+                            format!("{}{}", name, target),
+                            DatasetFormat::Otb,
+                            frames,
+                            groundtruth,
+                        )
+                    })
+                    .collect()
+            }
+            Some(DatasetFormat::Lasot) => {
+                let frames = list_images(&dir.join("img"))?;
+                let groundtruth = read_groundtruth(&dir.join("groundtruth.txt"))?;
+                Ok(vec![Self::new(
+                    name,
+                    DatasetFormat::Lasot,
+                    frames,
+                    groundtruth,
+                )?])
+            }
+            Some(DatasetFormat::Got10k) => {
+                let frames = list_images(dir)?;
+                let mut groundtruth = read_groundtruth(&dir.join("groundtruth.txt"))?;
+                if groundtruth.len() == 1 && frames.len() > 1 {
+                    return Err(format!(
+                        "{}: test split without groundtruth, use the evaluation server",
+                        dir.display()
+                    ));
+                }
+                // absence.label: 1 — цели в кадре нет
+                if let Ok(text) = fs::read_to_string(dir.join("absence.label")) {
+                    for (bbox, flag) in groundtruth.iter_mut().zip(text.lines()) {
+                        if flag.trim() == "1" {
+                            *bbox = Rect::default();
+                        }
+                    }
+                }
+                Ok(vec![Self::new(
+                    name,
+                    DatasetFormat::Got10k,
+                    frames,
+                    groundtruth,
+                )?])
+            }
+        }
+    }
+
+    fn new(
+        name: String,
+        format: DatasetFormat,
+        frames: Vec<PathBuf>,
+        groundtruth: Vec<Rect>,
+    ) -> Result<Self, String> {
+        if frames.len() != groundtruth.len() {
+            return Err(format!(
+                "{}: {} frames, {} boxes",
+                name,
+                frames.len(),
+                groundtruth.len()
+            ));
+        }
+        if !groundtruth.first().is_some_and(|&bbox| valid(bbox)) {
+            return Err(format!("{}: no box on the first frame", name));
+        }
+        Ok(Self {
+            name,
+            format,
+            frames,
+            groundtruth,
+        })
+    }
+
+    pub fn frame(&self, index: usize) -> opencv::Result<Mat> {
+        let path = self.frames[index].to_string_lossy();
+        let frame = imgcodecs::imread(&path, imgcodecs::IMREAD_COLOR)?;
+        if frame.empty() {
+            return Err(opencv::Error::new(
+                opencv::core::StsError,
+                format!("Can't read {}", path),
+            ));
+        }
+        Ok(frame)
+    }
+}
+
+fn valid(bbox: Rect) -> bool {
+    bbox.width > 0 && bbox.height > 0
+}
+
+/// Все последовательности набора: корень, его подкаталоги и подкаталоги второго уровня
+/// (классы LaSOT, сплиты GOT-10k).
+pub fn discover(root: &Path, depth: usize) -> Vec<PathBuf> {
+    if Sequence::detect(root).is_some() {
+        return vec![root.to_path_buf()];
+    }
+    if depth == 0 {
+        return Vec::new();
+    }
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs.iter()
+        .flat_map(|dir| discover(dir, depth - 1))
+        .collect()
+}
+
+fn center(bbox: Rect) -> (f32, f32) {
+    (
+        bbox.x as f32 + bbox.width as f32 / 2.0,
+        bbox.y as f32 + bbox.height as f32 / 2.0,
+    )
+}
+
+/// Результат прогона: рамки трекера на кадрах после первого и ошибки
+/// на кадрах с разметкой. На кадрах после потери IoU нулевой, ошибки бесконечны.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub boxes: Vec<Option<Rect>>,
+    pub ious: Vec<f32>,
+    pub center_errors: Vec<f32>,
+    /// Расстояние между центрами в долях размера эталонной рамки (LaSOT)
+    pub normalized_errors: Vec<f32>,
+    /// Чистое время `Tracker::update`, без чтения кадров
+    pub tracker_time: Duration,
+    pub updates: usize,
+}
+
+impl Run {
+    /// Доля кадров с IoU выше порога.
+    pub fn success_rate(&self, threshold: f32) -> f32 {
+        fraction(&self.ious, |iou| iou > threshold)
+    }
+
+    /// Площадь под кривой успеха по порогам 0, 0.05, ..., 1, как в OTB.
+    pub fn success_auc(&self) -> f32 {
+        (0..=20)
+            .map(|i| self.success_rate(i as f32 / 20.0))
+            .sum::<f32>()
+            / 21.0
+    }
+
+    /// Доля кадров, где центр ближе `px` пикселей к эталону.
+    pub fn precision(&self, px: f32) -> f32 {
+        fraction(&self.center_errors, |error| error <= px)
+    }
+
+    /// Площадь под кривой нормированной точности по порогам 0..0.5.
+    pub fn normalized_precision(&self) -> f32 {
+        (0..=50)
+            .map(|i| fraction(&self.normalized_errors, |error| error <= i as f32 / 100.0))
+            .sum::<f32>()
+            / 51.0
+    }
+
+    pub fn fps(&self) -> f32 {
+        let secs = self.tracker_time.as_secs_f32();
+        if secs > 0.0 {
+            self.updates as f32 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Первый кадр последовательности, на котором трекер сообщил о потере цели.
+    pub fn lost_at(&self) -> Option<usize> {
+        self.boxes.iter().position(Option::is_none).map(|i| i + 1)
+    }
+}
+
+fn fraction(values: &[f32], pred: impl Fn(f32) -> bool) -> f32 {
+    values.iter().filter(|&&v| pred(v)).count() as f32 / values.len().max(1) as f32
+}
+
+/// Прогоняет трекер по последовательности так же, как основной цикл: кадры по одному,
+/// без отбрасывания по времени; после потери цель больше не ищется.
+pub fn run(tracker: &mut dyn Tracker, sequence: &Sequence) -> opencv::Result<Run> {
+    tracker.init(&sequence.frame(0)?, sequence.groundtruth[0])?;
+
+    let mut run = Run {
+        boxes: Vec::with_capacity(sequence.frames.len() - 1),
+        ious: Vec::new(),
+        center_errors: Vec::new(),
+        normalized_errors: Vec::new(),
+        tracker_time: Duration::ZERO,
+        updates: 0,
+    };
+    let mut lost = false;
+    for (index, &truth) in sequence.groundtruth.iter().enumerate().skip(1) {
+        let bbox = if lost {
+            None
+        } else {
+            let frame = sequence.frame(index)?;
+            let start = Instant::now();
+            let result = tracker.update(&frame)?;
+            run.tracker_time += start.elapsed();
+            run.updates += 1;
+            result.map(|result| result.bbox)
+        };
+        lost = bbox.is_none();
+        run.boxes.push(bbox);
+
+        if !valid(truth) {
+            continue;
+        }
+        match bbox {
+            Some(bbox) => {
+                let ((x, y), (tx, ty)) = (center(bbox), center(truth));
+                run.ious.push(iou(&bbox, &truth));
+                run.center_errors.push((x - tx).hypot(y - ty));
+                run.normalized_errors
+                    .push(((x - tx) / truth.width as f32).hypot((y - ty) / truth.height as f32));
+            }
+            None => {
+                run.ious.push(0.0);
+                run.center_errors.push(f32::INFINITY);
+                run.normalized_errors.push(f32::INFINITY);
+            }
+        }
+    }
+    Ok(run)
+}
+
+/// Конфигурация трекеров `primary[+fallback]`; пороги и политика — из конфига.
+fn parse_tracker_spec(spec: &str, base: &TrackersConfig) -> Result<TrackersConfig, String> {
+    let kind = |name: &str| {
+        TrackerKind::deserialize(name.into_deserializer())
+            .map_err(|err: serde::de::value::Error| err.to_string())
+    };
+    let (primary, fallback) = match spec.split_once('+') {
+        Some((primary, fallback)) => (kind(primary)?, Some(kind(fallback)?)),
+        None => (kind(spec)?, None),
+    };
+    Ok(TrackersConfig {
+        primary,
+        fallback,
+        ..base.clone()
+    })
+}
+
+fn config_name(config: &TrackersConfig) -> String {
+    match config.fallback {
+        Some(fallback) => format!("{}+{}", config.primary.name(), fallback.name()),
+        None => config.primary.name().to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct SequenceReport {
+    name: String,
+    format: DatasetFormat,
+    frames: usize,
+    #[serde(rename = "success_auc")]
+    auc: f32,
+    #[serde(rename = "precision_20px")]
+    precision: f32,
+    #[serde(rename = "norm_precision")]
+    normalized_precision: f32,
+    fps: f32,
+    lost_at: Option<usize>,
+}
+
+struct ConfigReport {
+    name: String,
+    config: TrackersConfig,
+    sequences: Vec<SequenceReport>,
+    tracker_time: Duration,
+    updates: usize,
+}
+
+impl ConfigReport {
+    /// Метрики усредняются по последовательностям, FPS — по всем кадрам.
+    fn summary(&self) -> (f32, f32, f32, f32) {
+        let mean = |f: fn(&SequenceReport) -> f32| {
+            self.sequences.iter().map(f).sum::<f32>() / self.sequences.len().max(1) as f32
+        };
+        let secs = self.tracker_time.as_secs_f32();
+        (
+            mean(|s| s.auc),
+            mean(|s| s.precision),
+            mean(|s| s.normalized_precision),
+            if secs > 0.0 {
+                self.updates as f32 / secs
+            } else {
+                0.0
+            },
+        )
+    }
+}
+
+fn format_name(format: DatasetFormat) -> &'static str {
+    match format {
+        DatasetFormat::Otb => "otb",
+        DatasetFormat::Lasot => "lasot",
+        DatasetFormat::Got10k => "got10k",
+    }
+}
+
+fn write_reports(prefix: &str, dataset: &Path, reports: &[ConfigReport]) -> std::io::Result<()> {
+    let mut csv = String::from(
+        "config,sequence,format,frames,success_auc,precision_20px,norm_precision,fps,lost_at\n",
+    );
+    let mut summary =
+        String::from("config,sequences,success_auc,precision_20px,norm_precision,fps\n");
+    let mut configs = Vec::with_capacity(reports.len());
+
+    for report in reports {
+        let (auc, precision, normalized, fps) = report.summary();
+        let _ = writeln!(
+            summary,
+            "{},{},{:.4},{:.4},{:.4},{:.1}",
+            report.name,
+            report.sequences.len(),
+            auc,
+            precision,
+            normalized,
+            fps
+        );
+        for s in &report.sequences {
+            let lost_at = s.lost_at.map_or(String::new(), |frame| frame.to_string());
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{:.4},{:.4},{:.4},{:.1},{}",
+                report.name,
+                s.name,
+                format_name(s.format),
+                s.frames,
+                s.auc,
+                s.precision,
+                s.normalized_precision,
+                s.fps,
+                lost_at
+            );
+        }
+        configs.push(json!({
+            "name": report.name,
+            "primary": report.config.primary,
+            "fallback": report.config.fallback,
+            "primary_threshold": report.config.primary_threshold,
+            "fallback_threshold": report.config.fallback_threshold,
+            "summary": {
+                "sequences": report.sequences.len(),
+                "success_auc": auc,
+                "precision_20px": precision,
+                "norm_precision": normalized,
+                "fps": fps,
+            },
+            "sequences": report.sequences,
+        }));
+    }
+    let json = json!({ "dataset": dataset.display().to_string(), "configs": configs });
+
+    fs::write(format!("{}.json", prefix), json.to_string() + "\n")?;
+    fs::write(format!("{}.csv", prefix), csv)?;
+    fs::write(format!("{}.summary.csv", prefix), summary)?;
+    Ok(())
+}
+
+fn print_table(reports: &[ConfigReport]) {
+    println!(
+        "{:<20} {:>9} {:>8} {:>8} {:>8} {:>8}",
+        "config", "sequences", "AUC", "P@20", "norm P", "FPS"
+    );
+    for report in reports {
+        let (auc, precision, normalized, fps) = report.summary();
+        println!(
+            "{:<20} {:>9} {:>8.3} {:>8.3} {:>8.3} {:>8.1}",
+            report.name,
+            report.sequences.len(),
+            auc,
+            precision,
+            normalized,
+            fps
+        );
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
+
+/// Подкоманда `eval`; `args` — аргументы после её имени.
+pub fn main(base: &TrackersConfig, args: &[String]) -> opencv::Result<()> {
+    let mut dataset: Option<PathBuf> = None;
+    let mut configs: Vec<TrackersConfig> = Vec::new();
+    let mut only: Vec<String> = Vec::new();
+    let mut prefix: Option<String> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .unwrap_or_else(|| usage_error(&format!("Missing value for {}", arg)))
+        };
+        match arg.as_str() {
+            "--tracker" => configs
+                .push(parse_tracker_spec(&value(), base).unwrap_or_else(|err| usage_error(&err))),
+            "--sequence" => only.push(value()),
+            "--out" => prefix = Some(value()),
+            _ if dataset.is_none() && !arg.starts_with("--") => dataset = Some(PathBuf::from(arg)),
+            _ => usage_error(&format!("Unknown argument {}", arg)),
+        }
+    }
+    let dataset = dataset.unwrap_or_else(|| usage_error("Missing dataset"));
+    if configs.is_empty() {
+        configs.push(base.clone());
+    }
+    let prefix = prefix.unwrap_or_else(|| {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_secs());
+        format!("eval-{}", stamp)
+    });
+
+    let mut sequences = Vec::new();
+    for dir in discover(&dataset, 2) {
+        match Sequence::load(&dir) {
+            Ok(loaded) => sequences.extend(
+                loaded
+                    .into_iter()
+                    .filter(|s| only.is_empty() || only.contains(&s.name)),
+            ),
+            Err(err) => warn!("Skip {}", err),
+        }
+    }
+    if sequences.is_empty() {
+        usage_error(&format!("No sequences in {}", dataset.display()));
+    }
+    info!(
+        "Evaluating {} configs on {} sequences from {}",
+        configs.len(),
+        sequences.len(),
+        dataset.display()
+    );
+
+    let mut reports = Vec::new();
+    for config in configs {
+        let mut report = ConfigReport {
+            name: config_name(&config),
+            config,
+            sequences: Vec::new(),
+            tracker_time: Duration::ZERO,
+            updates: 0,
+        };
+        for sequence in &sequences {
+            // Новый трекер на каждую последовательность, чтобы не тянуть состояние
+            let mut tracker = create_tracker(&report.config)?;
+            let run = match run(tracker.as_mut(), sequence) {
+                Ok(run) => run,
+                Err(err) => {
+                    warn!("{} on {}: {}", report.name, sequence.name, err);
+                    continue;
+                }
+            };
+            info!(
+                "{} on {}: AUC {:.3}, P@20 {:.3}, {:.1} FPS",
+                report.name,
+                sequence.name,
+                run.success_auc(),
+                run.precision(PRECISION_PX),
+                run.fps()
+            );
+            report.tracker_time += run.tracker_time;
+            report.updates += run.updates;
+            report.sequences.push(SequenceReport {
+                name: sequence.name.clone(),
+                format: sequence.format,
+                frames: sequence.frames.len(),
+                auc: run.success_auc(),
+                precision: run.precision(PRECISION_PX),
+                normalized_precision: run.normalized_precision(),
+                fps: run.fps(),
+                lost_at: run.lost_at(),
+            });
+        }
+        reports.push(report);
+    }
+
+    print_table(&reports);
+    match write_reports(&prefix, &dataset, &reports) {
+        Ok(()) => info!(
+            "Report written to {}.json, {}.csv and {}.summary.csv",
+            prefix, prefix, prefix
+        ),
+        Err(err) => warn!("Can't write report {}: {}", prefix, err),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trackers::TrackResult;
+    use std::collections::VecDeque;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+    }
+
+    /// Трекер, отдающий заранее заданные рамки.
+    struct Scripted(VecDeque<Option<Rect>>);
+
+    impl Tracker for Scripted {
+        fn init(&mut self, _frame: &Mat, _bbox: Rect) -> opencv::Result<()> {
+            Ok(())
+        }
+
+        fn update(&mut self, _frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+            let bbox = self.0.pop_front().expect("Unexpected update");
+            Ok(bbox.map(|bbox| TrackResult {
+                bbox,
+                score: 1.0,
+                source: TrackerKind::Kcf,
+                inference_time: Duration::ZERO,
+            }))
+        }
+    }
+
+    #[test]
+    fn discover_fixtures() {
+        let names: Vec<String> = discover(&fixtures(), 2)
+            .iter()
+            .flat_map(|dir| Sequence::load(dir).unwrap())
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["scale", "translate"]);
+    }
+
+    #[test]
+    fn parse_groundtruth() {
+        assert_eq!(parse_rect("10,20,30,40"), Some(Rect::new(10, 20, 30, 40)));
+        assert_eq!(
+            parse_rect("10\t20\t30.4\t39.6"),
+            Some(Rect::new(10, 20, 30, 40))
+        );
+        assert_eq!(parse_rect("10 20 30"), None);
+        assert_eq!(parse_rect("NaN,x,1,2"), None);
+    }
+
+    #[test]
+    fn success_metrics() {
+        let run = Run {
+            boxes: vec![None; 4],
+            ious: vec![1.0, 0.82, 0.33, 0.0],
+            center_errors: vec![0.0, 5.0, 25.0, f32::INFINITY],
+            normalized_errors: vec![0.0, 0.105, 0.6, f32::INFINITY],
+            tracker_time: Duration::from_secs(2),
+            updates: 4,
+        };
+        assert_eq!(run.success_rate(0.5), 0.5);
+        assert!((run.success_auc() - (20.0 + 17.0 + 7.0) / 84.0).abs() < 1e-6);
+        assert_eq!(run.precision(20.0), 0.5);
+        assert!((run.normalized_precision() - (51.0 + 40.0) / 204.0).abs() < 1e-6);
+        assert_eq!(run.fps(), 2.0);
+        assert_eq!(run.lost_at(), Some(1));
+    }
+
+    #[test]
+    fn precision_from_run() {
+        let truth = Rect::new(100, 100, 40, 20);
+        let mut sequence = Sequence::load(&fixtures().join("translate"))
+            .unwrap()
+            .remove(0);
+        sequence.frames.truncate(7);
+        sequence.groundtruth = vec![truth; 7];
+        sequence.groundtruth[3] = Rect::new(0, 0, 0, 0);
+
+        let mut tracker = Scripted(VecDeque::from([
+            Some(truth),
+            // Сдвиг (12, 16): ровно 20 px, но 0.85 в долях рамки
+            Some(Rect::new(112, 116, 40, 20)),
+            // Кадр без разметки в метрики не входит
+            Some(Rect::new(300, 300, 40, 20)),
+            Some(Rect::new(104, 100, 40, 20)),
+            None,
+        ]));
+        let run = run(&mut tracker, &sequence).unwrap();
+
+        assert_eq!(run.updates, 5);
+        assert_eq!(run.lost_at(), Some(5));
+        assert_eq!(
+            run.center_errors,
+            [0.0, 20.0, 4.0, f32::INFINITY, f32::INFINITY]
+        );
+        assert_eq!(run.precision(20.0), 0.6);
+        assert_eq!(run.precision(19.0), 0.4);
+        // Нулевая ошибка проходит все 51 порог, 0.1 — пороги от 0.1 до 0.5
+        assert!((run.normalized_precision() - (51.0 + 41.0) / 255.0).abs() < 1e-6);
+    }
+}
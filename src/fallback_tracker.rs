@@ -0,0 +1,303 @@
+use crate::config::{FallbackPolicy, TrackersConfig};
+use crate::trackers::{TrackResult, Tracker};
+use log::debug;
+use opencv::core::{Mat, Rect};
+use opencv::prelude::*;
+use ticky::Stopwatch;
+
+/// Последний кадр, на котором основной трекер уверенно держал цель.
+struct Snapshot {
+    frame: Mat,
+    bbox: Rect,
+}
+
+/// Основной трекер + запасной, который подхватывает цель,
+/// когда уверенность основного падает ниже порога.
+///
+/// Запасной всегда инициализируется по снимку последнего уверенного кадра,
+/// а не по текущему: на текущем кадре основной трекер уже ошибся.
+/// Без запасного трекера цель теряется, как при `GiveUp`.
+pub struct FallbackTracker {
+    primary: Box<dyn Tracker>,
+    fallback: Option<Box<dyn Tracker>>,
+    primary_threshold: f32,
+    fallback_threshold: f32,
+    policy: FallbackPolicy,
+    on_fallback: bool,
+    snapshot: Option<Snapshot>,
+}
+
+impl FallbackTracker {
+    pub fn new(
+        primary: Box<dyn Tracker>,
+        fallback: Option<Box<dyn Tracker>>,
+        config: &TrackersConfig,
+    ) -> Self {
+        Self {
+            primary,
+            fallback,
+            primary_threshold: config.primary_threshold,
+            fallback_threshold: config.fallback_threshold,
+            policy: config.policy,
+            on_fallback: false,
+            snapshot: None,
+        }
+    }
+
+    fn return_none(&mut self) -> opencv::Result<Option<TrackResult>> {
+        self.on_fallback = false;
+        self.snapshot = None;
+        Ok(None)
+    }
+
+    fn update_fallback(&mut self, frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+        let Some(fallback) = self.fallback.as_mut() else {
+            return self.return_none();
+        };
+        match fallback.update(frame)? {
+            Some(result) => {
+                debug!("Score fallback tracker: {:?}", result.score);
+                if result.score >= self.fallback_threshold {
+                    Ok(Some(result))
+                } else {
+                    self.return_none()
+                }
+            }
+            None => self.return_none(),
+        }
+    }
+
+    fn update_inner(&mut self, frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+        if self.on_fallback {
+            return self.update_fallback(frame);
+        }
+
+        if let Some(result) = self.primary.update(frame)? {
+            debug!("Score primary tracker: {:?}", result.score);
+            if result.score >= self.primary_threshold {
+                self.snapshot = Some(Snapshot {
+                    frame: frame.clone(),
+                    bbox: result.bbox,
+                });
+                return Ok(Some(result));
+            }
+        }
+
+        if self.policy == FallbackPolicy::GiveUp {
+            return self.return_none();
+        }
+
+        let (Some(fallback), Some(snapshot)) = (self.fallback.as_mut(), &self.snapshot) else {
+            return self.return_none();
+        };
+
+        debug!("Init fallback tracker");
+        fallback.init(&snapshot.frame, snapshot.bbox)?;
+        let result = self.update_fallback(frame)?;
+
+        if let Some(result) = result {
+            match self.policy {
+                // Возвращаемся на основной трекер, чтобы не застрять на более медленном запасном
+                FallbackPolicy::ReinitPrimary => self.primary.init(frame, result.bbox)?,
+                FallbackPolicy::StayOnFallback => self.on_fallback = true,
+                FallbackPolicy::GiveUp => {}
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl Tracker for FallbackTracker {
+    fn init(&mut self, frame: &Mat, bbox: Rect) -> opencv::Result<()> {
+        self.primary.init(frame, bbox)?;
+        self.on_fallback = false;
+        self.snapshot = Some(Snapshot {
+            frame: frame.clone(),
+            bbox,
+        });
+        Ok(())
+    }
+
+    fn update(&mut self, frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+        // Время всего обновления, включая инициализацию запасного трекера
+        let mut sw = Stopwatch::start_new();
+        let result = self.update_inner(frame)?;
+        sw.stop();
+        Ok(result.map(|result| TrackResult {
+            inference_time: sw.elapsed,
+            ..result
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TrackerKind;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    /// Что происходило с трекером: `init` по рамке и число `update`.
+    #[derive(Debug, Default)]
+    struct Log {
+        inits: Vec<Rect>,
+        updates: usize,
+    }
+
+    /// Трекер с заранее заданной уверенностью на каждый `update`;
+    /// рамка сдвигается на пиксель от последней инициализации.
+    struct Scripted {
+        kind: TrackerKind,
+        scores: VecDeque<Option<f32>>,
+        bbox: Rect,
+        log: Rc<RefCell<Log>>,
+    }
+
+    impl Tracker for Scripted {
+        fn init(&mut self, _frame: &Mat, bbox: Rect) -> opencv::Result<()> {
+            self.bbox = bbox;
+            self.log.borrow_mut().inits.push(bbox);
+            Ok(())
+        }
+
+        fn update(&mut self, _frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+            self.log.borrow_mut().updates += 1;
+            let score = self.scores.pop_front().expect("Unexpected update");
+            self.bbox.x += 1;
+            Ok(score.map(|score| TrackResult {
+                bbox: self.bbox,
+                score,
+                source: self.kind,
+                inference_time: Duration::ZERO,
+            }))
+        }
+    }
+
+    fn scripted(kind: TrackerKind, scores: &[Option<f32>]) -> (Box<dyn Tracker>, Rc<RefCell<Log>>) {
+        let log = Rc::new(RefCell::new(Log::default()));
+        let tracker = Scripted {
+            kind,
+            scores: scores.iter().copied().collect(),
+            bbox: Rect::default(),
+            log: log.clone(),
+        };
+        (Box::new(tracker), log)
+    }
+
+    fn config(policy: FallbackPolicy) -> TrackersConfig {
+        TrackersConfig {
+            primary_threshold: 0.5,
+            fallback_threshold: 0.6,
+            policy,
+            ..TrackersConfig::default()
+        }
+    }
+
+    const START: Rect = Rect {
+        x: 10,
+        y: 20,
+        width: 30,
+        height: 40,
+    };
+
+    /// Уверенный кадр, затем провал основного: запасной стартует по снимку уверенного кадра.
+    fn handover(
+        policy: FallbackPolicy,
+        primary: &[Option<f32>],
+        fallback: &[Option<f32>],
+    ) -> (FallbackTracker, Rc<RefCell<Log>>, Rc<RefCell<Log>>) {
+        let (primary, primary_log) = scripted(TrackerKind::VitInt8, primary);
+        let (fallback, fallback_log) = scripted(TrackerKind::Vit, fallback);
+        let mut tracker = FallbackTracker::new(primary, Some(fallback), &config(policy));
+        tracker.init(&Mat::default(), START).unwrap();
+
+        let confident = tracker.update(&Mat::default()).unwrap().unwrap();
+        assert_eq!(
+            (confident.source, confident.bbox.x),
+            (TrackerKind::VitInt8, 11)
+        );
+        let handed = tracker.update(&Mat::default()).unwrap().unwrap();
+        assert_eq!(handed.source, TrackerKind::Vit);
+        assert_eq!(fallback_log.borrow().inits, [confident.bbox]);
+        assert_eq!(handed.bbox.x, confident.bbox.x + 1);
+        (tracker, primary_log, fallback_log)
+    }
+
+    #[test]
+    fn reinit_primary_returns_to_primary() {
+        let (mut tracker, primary_log, fallback_log) = handover(
+            FallbackPolicy::ReinitPrimary,
+            &[Some(0.9), Some(0.2), Some(0.8)],
+            &[Some(0.7)],
+        );
+        assert_eq!(primary_log.borrow().inits, [START, Rect { x: 12, ..START }]);
+
+        let result = tracker.update(&Mat::default()).unwrap().unwrap();
+        assert_eq!((result.source, result.bbox.x), (TrackerKind::VitInt8, 13));
+        assert_eq!(fallback_log.borrow().updates, 1);
+    }
+
+    #[test]
+    fn stay_on_fallback_skips_primary() {
+        let (mut tracker, primary_log, fallback_log) = handover(
+            FallbackPolicy::StayOnFallback,
+            &[Some(0.9), Some(0.2)],
+            &[Some(0.7), Some(0.65), Some(0.3)],
+        );
+        assert_eq!(primary_log.borrow().inits, [START]);
+
+        assert_eq!(
+            tracker.update(&Mat::default()).unwrap().unwrap().source,
+            TrackerKind::Vit
+        );
+        // Запасной потерял цель — трекер сбрасывается, основной не вызывался
+        assert!(tracker.update(&Mat::default()).unwrap().is_none());
+        assert_eq!(primary_log.borrow().updates, 2);
+        assert_eq!(fallback_log.borrow().updates, 3);
+    }
+
+    #[test]
+    fn give_up_never_uses_fallback() {
+        let (primary, _) = scripted(TrackerKind::VitInt8, &[Some(0.9), Some(0.2)]);
+        let (fallback, fallback_log) = scripted(TrackerKind::Vit, &[]);
+        let mut tracker =
+            FallbackTracker::new(primary, Some(fallback), &config(FallbackPolicy::GiveUp));
+        tracker.init(&Mat::default(), START).unwrap();
+
+        assert!(tracker.update(&Mat::default()).unwrap().is_some());
+        assert!(tracker.update(&Mat::default()).unwrap().is_none());
+        assert!(fallback_log.borrow().inits.is_empty());
+    }
+
+    #[test]
+    fn weak_fallback_loses_target() {
+        let (primary, _) = scripted(TrackerKind::VitInt8, &[None, Some(0.1)]);
+        let (fallback, fallback_log) = scripted(TrackerKind::Vit, &[Some(0.5)]);
+        let mut tracker = FallbackTracker::new(
+            primary,
+            Some(fallback),
+            &config(FallbackPolicy::ReinitPrimary),
+        );
+        tracker.init(&Mat::default(), START).unwrap();
+
+        // Основной сам сообщил о потере, запасной стартует со снимка `init` и не дотягивает до порога
+        assert!(tracker.update(&Mat::default()).unwrap().is_none());
+        assert_eq!(fallback_log.borrow().inits, [START]);
+        // Снимок сброшен: без нового уверенного кадра запасной больше не инициализируется
+        assert!(tracker.update(&Mat::default()).unwrap().is_none());
+        assert_eq!(fallback_log.borrow().inits.len(), 1);
+    }
+
+    #[test]
+    fn threshold_applies_without_fallback() {
+        let (primary, _) = scripted(TrackerKind::Kcf, &[Some(0.9), Some(0.2)]);
+        let mut tracker =
+            FallbackTracker::new(primary, None, &config(FallbackPolicy::ReinitPrimary));
+        tracker.init(&Mat::default(), START).unwrap();
+
+        assert!(tracker.update(&Mat::default()).unwrap().is_some());
+        assert!(tracker.update(&Mat::default()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,499 @@
+//! Замкнутое управление поворотным подвесом: смещение цели от центра кадра
+//! превращается в скорости pan/tilt, которые уходят в привод через [`Actuator`].
+
+use crate::camera::{Camera, TargetOffset};
+use crate::config::{ActuatorKind, GimbalConfig, LostBehavior, PidGains};
+use crate::pelco_d::{self, PelcoDActuator};
+use crate::sbgc::{self, SbgcActuator};
+use crate::serial;
+use log::{debug, info, warn};
+use opencv::core::Rect;
+use std::fs::File;
+use std::io;
+use std::time::Instant;
+
+/// Команда скорости, градусы в секунду: pan положителен вправо, tilt — вверх.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateCommand {
+    pub pan: f32,
+    pub tilt: f32,
+}
+
+/// Привод подвеса.
+pub trait Actuator: Send {
+    fn set_rates(&mut self, command: RateCommand) -> io::Result<()>;
+
+    /// Текущие углы pan/tilt в градусах, если привод их сообщает.
+    fn angles(&mut self) -> Option<(f32, f32)> {
+        None
+    }
+}
+
+/// ПИД по одной оси; ошибка и выход в градусах и градусах в секунду.
+#[derive(Debug, Clone)]
+pub struct Pid {
+    gains: PidGains,
+    integral: f32,
+    prev_error: Option<f32>,
+}
+
+impl Pid {
+    pub fn new(gains: PidGains) -> Self {
+        Self {
+            gains,
+            integral: 0.0,
+            prev_error: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.prev_error = None;
+    }
+
+    /// `limit` — предел выхода; интеграл не растёт, пока выход в насыщении
+    /// в сторону ошибки, и ограничен `integral_limit`.
+    pub fn update(&mut self, error: f32, dt: f32, limit: f32, integral_limit: f32) -> f32 {
+        let derivative = match self.prev_error {
+            Some(prev) if dt > 0.0 => (error - prev) / dt,
+            _ => 0.0,
+        };
+        self.prev_error = Some(error);
+
+        let output = |integral: f32| {
+            self.gains.kp * error + self.gains.ki * integral + self.gains.kd * derivative
+        };
+        let integral = (self.integral + error * dt).clamp(-integral_limit, integral_limit);
+        let candidate = output(integral);
+        let saturated = candidate.abs() > limit && candidate.signum() == error.signum();
+        if !saturated {
+            self.integral = integral;
+        }
+        output(self.integral).clamp(-limit, limit)
+    }
+
+    /// Внутри зоны нечувствительности выход нулевой, а интеграл замораживается,
+    /// иначе накопленная I-составляющая продолжала бы медленно вести подвес.
+    pub fn update_outside(
+        &mut self,
+        error: f32,
+        band: f32,
+        dt: f32,
+        limit: f32,
+        integral_limit: f32,
+    ) -> f32 {
+        if error.abs() < band {
+            self.prev_error = Some(error);
+            return 0.0;
+        }
+        self.update(error, dt, limit, integral_limit)
+    }
+}
+
+fn deadband(error: f32, band: f32) -> f32 {
+    if error.abs() < band { 0.0 } else { error }
+}
+
+/// Ограничение ускорения: выход меняется не быстрее `max_accel` град/с².
+fn slew(previous: f32, target: f32, max_accel: f32, dt: f32) -> f32 {
+    if max_accel <= 0.0 {
+        return target;
+    }
+    let step = max_accel * dt;
+    target.clamp(previous - step, previous + step)
+}
+
+/// Регулятор pan/tilt. Время передаётся явно, чтобы прогон на записи был детерминированным.
+pub struct GimbalController {
+    config: GimbalConfig,
+    pan: Pid,
+    tilt: Pid,
+    output: RateCommand,
+    /// Углы, проинтегрированные по командам, — для приводов без обратной связи
+    estimate: (f32, f32),
+    /// Сколько секунд цели нет
+    lost_for: f32,
+}
+
+impl GimbalController {
+    pub fn new(config: &GimbalConfig) -> Self {
+        Self {
+            config: config.clone(),
+            pan: Pid::new(config.pan),
+            tilt: Pid::new(config.tilt),
+            output: RateCommand::default(),
+            estimate: (0.0, 0.0),
+            lost_for: 0.0,
+        }
+    }
+
+    /// `angles` — положение от привода, если он его сообщает; `dt` — секунды с прошлого вызова.
+    pub fn update(
+        &mut self,
+        offset: Option<TargetOffset>,
+        angles: Option<(f32, f32)>,
+        dt: f32,
+    ) -> RateCommand {
+        let config = &self.config;
+        let limit = config.max_rate_deg_s;
+        let angles = angles.unwrap_or(self.estimate);
+
+        let target = match offset {
+            Some(offset) => {
+                self.lost_for = 0.0;
+                let band = config.deadband_deg;
+                RateCommand {
+                    pan: self.pan.update_outside(
+                        offset.yaw_deg,
+                        band,
+                        dt,
+                        limit,
+                        config.integral_limit,
+                    ),
+                    tilt: self.tilt.update_outside(
+                        offset.pitch_deg,
+                        band,
+                        dt,
+                        limit,
+                        config.integral_limit,
+                    ),
+                }
+            }
+            None => {
+                if self.lost_for == 0.0 {
+                    self.pan.reset();
+                    self.tilt.reset();
+                }
+                self.lost_for += dt;
+                match config.lost_behavior {
+                    LostBehavior::Center if self.lost_for >= config.center_after_secs => {
+                        // Возврат в ноль пропорционально текущему углу
+                        let back = |angle: f32| {
+                            (-deadband(angle, config.deadband_deg) * config.center_kp)
+                                .clamp(-limit, limit)
+                        };
+                        RateCommand {
+                            pan: back(angles.0),
+                            tilt: back(angles.1),
+                        }
+                    }
+                    _ => RateCommand::default(),
+                }
+            }
+        };
+
+        self.output = RateCommand {
+            pan: slew(self.output.pan, target.pan, config.max_accel_deg_s2, dt),
+            tilt: slew(self.output.tilt, target.tilt, config.max_accel_deg_s2, dt),
+        };
+        self.estimate.0 += self.output.pan * dt;
+        self.estimate.1 += self.output.tilt * dt;
+        self.output
+    }
+}
+
+/// Модель привода для проверки без железа: скорость догоняет команду
+/// с постоянной времени `lag_secs`, углы интегрируются.
+pub struct SimActuator {
+    pub pan: f32,
+    pub tilt: f32,
+    rate: RateCommand,
+    command: RateCommand,
+    lag_secs: f32,
+    last: Option<Instant>,
+}
+
+impl SimActuator {
+    pub fn new(lag_secs: f32) -> Self {
+        Self {
+            pan: 0.0,
+            tilt: 0.0,
+            rate: RateCommand::default(),
+            command: RateCommand::default(),
+            lag_secs,
+            last: None,
+        }
+    }
+
+    /// Продвигает модель на `dt` секунд с последней командой.
+    pub fn advance(&mut self, dt: f32) {
+        let k = if self.lag_secs > 0.0 {
+            (dt / self.lag_secs).min(1.0)
+        } else {
+            1.0
+        };
+        self.rate.pan += (self.command.pan - self.rate.pan) * k;
+        self.rate.tilt += (self.command.tilt - self.rate.tilt) * k;
+        self.pan += self.rate.pan * dt;
+        self.tilt += self.rate.tilt * dt;
+    }
+
+    /// Продвигает модель до текущего момента реального времени.
+    pub fn sync(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last {
+            self.advance(now.duration_since(last).as_secs_f32());
+        }
+        self.last = Some(now);
+    }
+
+    /// Команда без продвижения по реальному времени — для пошаговых прогонов.
+    pub fn command(&mut self, command: RateCommand) {
+        self.command = command;
+    }
+}
+
+impl Actuator for SimActuator {
+    fn set_rates(&mut self, command: RateCommand) -> io::Result<()> {
+        self.sync();
+        self.command = command;
+        Ok(())
+    }
+
+    fn angles(&mut self) -> Option<(f32, f32)> {
+        Some((self.pan, self.tilt))
+    }
+}
+
+/// Порт привода; `"pty"` — псевдотерминал с эмулятором устройства на другой стороне.
+fn open_port(config: &GimbalConfig) -> io::Result<File> {
+    if config.serial != "pty" {
+        return serial::open(&config.serial, config.baud);
+    }
+
+    let pty = serial::loopback()?;
+    info!("Gimbal loopback on {}", pty.path);
+    let port = serial::open(&pty.path, config.baud)?;
+    let (master, lag, max_rate) = (pty.master, config.sim_lag_secs, config.pelco_max_rate_deg_s);
+    match config.actuator {
+        ActuatorKind::Sbgc => std::thread::spawn(move || sbgc::emulate(master, lag)),
+        _ => std::thread::spawn(move || pelco_d::monitor(master, max_rate)),
+    };
+    Ok(port)
+}
+
+pub fn create_actuator(config: &GimbalConfig) -> io::Result<Box<dyn Actuator>> {
+    Ok(match config.actuator {
+        ActuatorKind::Sim => Box::new(SimActuator::new(config.sim_lag_secs)),
+        ActuatorKind::Sbgc => Box::new(SbgcActuator::new(open_port(config)?)?),
+        ActuatorKind::PelcoD => Box::new(PelcoDActuator::new(
+            open_port(config)?,
+            config.pelco_address,
+            config.pelco_max_rate_deg_s,
+        )),
+    })
+}
+
+/// Регулятор вместе с приводом; вызывается на каждом кадре.
+pub struct Gimbal {
+    controller: GimbalController,
+    actuator: Box<dyn Actuator>,
+    camera: Camera,
+    last_update: Option<Instant>,
+}
+
+impl Gimbal {
+    pub fn new(config: &GimbalConfig, camera: Camera) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        match create_actuator(config) {
+            Ok(actuator) => {
+                info!("Gimbal control via {:?} actuator", config.actuator);
+                Some(Self {
+                    controller: GimbalController::new(config),
+                    actuator,
+                    camera,
+                    last_update: None,
+                })
+            }
+            Err(err) => {
+                warn!("Can't open gimbal actuator {:?}: {}", config.actuator, err);
+                None
+            }
+        }
+    }
+
+    pub fn update(&mut self, target: Option<Rect>, width: i32, height: i32) {
+        let now = Instant::now();
+        // После долгой паузы (зависание конвейера) шаг ограничивается, чтобы не раскачать ПИД
+        let dt = self
+            .last_update
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32())
+            .min(0.5);
+        self.last_update = Some(now);
+
+        let offset = target.map(|bbox| self.camera.offset(bbox, width, height));
+        let angles = self.actuator.angles();
+        let command = self.controller.update(offset, angles, dt);
+        debug!("gimbal: {:?} -> {:?}", offset, command);
+        if let Err(err) = self.actuator.set_rates(command) {
+            warn!("Can't send gimbal command: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.02;
+
+    fn offset(yaw_deg: f32, pitch_deg: f32) -> TargetOffset {
+        TargetOffset {
+            yaw_deg,
+            pitch_deg,
+            ..TargetOffset::default()
+        }
+    }
+
+    /// Неподвижная цель под углом `target`; подвес — модель привода с запаздыванием.
+    fn track(config: &GimbalConfig, target: (f32, f32), secs: f32) -> SimActuator {
+        let mut controller = GimbalController::new(config);
+        let mut actuator = SimActuator::new(config.sim_lag_secs);
+        for _ in 0..(secs / DT) as usize {
+            let seen = offset(target.0 - actuator.pan, target.1 - actuator.tilt);
+            let command = controller.update(Some(seen), actuator.angles(), DT);
+            actuator.command(command);
+            actuator.advance(DT);
+        }
+        actuator
+    }
+
+    #[test]
+    fn converges_on_target() {
+        let config = GimbalConfig::default();
+        let actuator = track(&config, (25.0, -10.0), 5.0);
+        assert!(
+            (actuator.pan - 25.0).abs() <= config.deadband_deg,
+            "pan {}",
+            actuator.pan
+        );
+        assert!(
+            (actuator.tilt + 10.0).abs() <= config.deadband_deg,
+            "tilt {}",
+            actuator.tilt
+        );
+    }
+
+    #[test]
+    fn deadband_freezes_integral() {
+        let mut pid = Pid::new(PidGains {
+            kp: 1.0,
+            ki: 1.0,
+            kd: 0.0,
+        });
+        pid.update_outside(2.0, 0.5, 0.1, 90.0, 20.0);
+        assert!((pid.integral - 0.2).abs() < 1e-6);
+        for _ in 0..100 {
+            assert_eq!(pid.update_outside(0.4, 0.5, 0.1, 90.0, 20.0), 0.0);
+        }
+        assert!((pid.integral - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn no_windup_while_saturated() {
+        let mut pid = Pid::new(PidGains {
+            kp: 2.0,
+            ki: 1.0,
+            kd: 0.0,
+        });
+        for _ in 0..100 {
+            assert_eq!(pid.update(10.0, 0.1, 5.0, 20.0), 5.0);
+        }
+        assert_eq!(pid.integral, 0.0);
+        // Без накопленного интеграла выход сразу меняет знак вслед за ошибкой
+        assert!(pid.update(-1.0, 0.1, 5.0, 20.0) < 0.0);
+
+        // Интеграл ограничен и без насыщения выхода
+        let mut pid = Pid::new(PidGains {
+            kp: 0.0,
+            ki: 0.1,
+            kd: 0.0,
+        });
+        for _ in 0..1000 {
+            pid.update(10.0, 0.1, 90.0, 20.0);
+        }
+        assert_eq!(pid.integral, 20.0);
+    }
+
+    #[test]
+    fn acceleration_is_limited() {
+        let config = GimbalConfig {
+            max_accel_deg_s2: 100.0,
+            ..GimbalConfig::default()
+        };
+        let mut controller = GimbalController::new(&config);
+        let step = config.max_accel_deg_s2 * DT;
+        let mut previous = RateCommand::default();
+        for i in 0..100 {
+            let target = if i < 50 {
+                offset(40.0, -40.0)
+            } else {
+                offset(-40.0, 40.0)
+            };
+            let command = controller.update(Some(target), None, DT);
+            assert!(
+                (command.pan - previous.pan).abs() <= step + 1e-4,
+                "{:?} after {:?}",
+                command,
+                previous
+            );
+            assert!(
+                (command.tilt - previous.tilt).abs() <= step + 1e-4,
+                "{:?} after {:?}",
+                command,
+                previous
+            );
+            previous = command;
+        }
+        let first = GimbalController::new(&config).update(Some(offset(40.0, -40.0)), None, DT);
+        assert!(
+            (first.pan - step).abs() < 1e-4 && (first.tilt + step).abs() < 1e-4,
+            "{:?}",
+            first
+        );
+    }
+
+    fn lost_commands(lost_behavior: LostBehavior) -> Vec<RateCommand> {
+        let config = GimbalConfig {
+            lost_behavior,
+            max_accel_deg_s2: 0.0,
+            ..GimbalConfig::default()
+        };
+        let mut controller = GimbalController::new(&config);
+        controller.update(Some(offset(0.0, 0.0)), Some((15.0, -8.0)), DT);
+        (0..(3.0 / DT) as usize)
+            .map(|_| controller.update(None, Some((15.0, -8.0)), DT))
+            .collect()
+    }
+
+    #[test]
+    fn hold_stays_after_loss() {
+        assert!(
+            lost_commands(LostBehavior::Hold)
+                .iter()
+                .all(|&command| command == RateCommand::default())
+        );
+    }
+
+    #[test]
+    fn center_after_timeout() {
+        let config = GimbalConfig::default();
+        let commands = lost_commands(LostBehavior::Center);
+        let wait = (config.center_after_secs / DT) as usize - 1;
+        assert!(
+            commands[..wait]
+                .iter()
+                .all(|&command| command == RateCommand::default())
+        );
+        let back = *commands.last().unwrap();
+        assert_eq!(
+            back,
+            RateCommand {
+                pan: -15.0 * config.center_kp,
+                tilt: 8.0 * config.center_kp
+            }
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::camera::{Camera, TargetOffset};
+use crate::config::HudConfig;
+use crate::overlay::{Style, scalar};
+use crate::trackers::TrackResult;
+use opencv::core::{Point, Rect};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockState {
+    NoTarget,
+    /// Цель ведётся меньше `lock_frames` кадров подряд
+    Acquiring,
+    Locked,
+}
+
+impl LockState {
+    pub fn name(self) -> &'static str {
+        match self {
+            LockState::NoTarget => "NO TARGET",
+            LockState::Acquiring => "ACQUIRING",
+            LockState::Locked => "LOCKED",
+        }
+    }
+}
+
+/// Прицельная марка, смещение цели от центра кадра, вектор движения и индикатор захвата.
+pub struct Hud {
+    config: HudConfig,
+    camera: Camera,
+    track_id: Option<u64>,
+    prev_center: Option<(f32, f32)>,
+    /// Сглаженная скорость центра цели, пиксели за кадр
+    velocity: (f32, f32),
+    tracked_frames: u32,
+}
+
+impl Hud {
+    pub fn new(config: &HudConfig, camera: Camera) -> Self {
+        Self {
+            config: config.clone(),
+            camera,
+            track_id: None,
+            prev_center: None,
+            velocity: (0.0, 0.0),
+            tracked_frames: 0,
+        }
+    }
+
+    pub fn lock_state(&self) -> LockState {
+        match self.tracked_frames {
+            0 => LockState::NoTarget,
+            n if n < self.config.lock_frames => LockState::Acquiring,
+            _ => LockState::Locked,
+        }
+    }
+
+    /// Обновляет скорость и счётчик захвата; вызывается на каждом кадре.
+    pub fn update(&mut self, target: Option<&TrackResult>, track_id: Option<u64>) {
+        let Some(target) = target else {
+            self.prev_center = None;
+            self.velocity = (0.0, 0.0);
+            self.tracked_frames = 0;
+            return;
+        };
+        if track_id != self.track_id {
+            self.track_id = track_id;
+            self.prev_center = None;
+            self.velocity = (0.0, 0.0);
+            self.tracked_frames = 0;
+        }
+
+        let bbox = target.bbox;
+        let center = (
+            bbox.x as f32 + bbox.width as f32 / 2.0,
+            bbox.y as f32 + bbox.height as f32 / 2.0,
+        );
+        if let Some(prev) = self.prev_center {
+            let k = self.config.motion_smoothing.clamp(0.0, 0.99);
+            self.velocity = (
+                self.velocity.0 * k + (center.0 - prev.0) * (1.0 - k),
+                self.velocity.1 * k + (center.1 - prev.1) * (1.0 - k),
+            );
+        }
+        self.prev_center = Some(center);
+        self.tracked_frames = self.tracked_frames.saturating_add(1);
+    }
+
+    pub fn draw(
+        &self,
+        frame: &mut Mat,
+        target: Option<&TrackResult>,
+        style: &Style,
+    ) -> opencv::Result<()> {
+        let config = &self.config;
+        let (width, height) = (frame.cols(), frame.rows());
+        let center = Point::new(width / 2, height / 2);
+        let size = style.px(config.reticle_size);
+        let gap = size / 4;
+        let reticle = scalar(config.reticle_color);
+
+        // Перекрестие с разрывом в центре, чтобы не закрывать цель
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            imgproc::line(
+                frame,
+                Point::new(center.x + dx * gap, center.y + dy * gap),
+                Point::new(center.x + dx * size, center.y + dy * size),
+                reticle,
+                style.thickness,
+                imgproc::LINE_AA,
+                0,
+            )?;
+        }
+
+        let state = self.lock_state();
+        let state_color = scalar(match state {
+            LockState::NoTarget => config.lost_color,
+            LockState::Acquiring => config.acquiring_color,
+            LockState::Locked => config.locked_color,
+        });
+
+        let mut lines = vec![state.name().to_string()];
+        if let Some(target) = target {
+            let bbox = target.bbox;
+            let target_center = Point::new(bbox.x + bbox.width / 2, bbox.y + bbox.height / 2);
+            imgproc::line(
+                frame,
+                center,
+                target_center,
+                reticle,
+                (style.thickness / 2).max(1),
+                imgproc::LINE_AA,
+                0,
+            )?;
+
+            if state == LockState::Locked {
+                draw_brackets(frame, bbox, state_color, style.thickness * 2)?;
+            }
+
+            let arrow_end = Point::new(
+                target_center.x + (self.velocity.0 * config.arrow_frames) as i32,
+                target_center.y + (self.velocity.1 * config.arrow_frames) as i32,
+            );
+            if arrow_end != target_center {
+                imgproc::arrowed_line(
+                    frame,
+                    target_center,
+                    arrow_end,
+                    scalar(config.motion_color),
+                    style.thickness,
+                    imgproc::LINE_AA,
+                    0,
+                    0.2,
+                )?;
+            }
+
+            let TargetOffset {
+                dx_px,
+                dy_px,
+                yaw_deg,
+                pitch_deg,
+            } = self.camera.offset(bbox, width, height);
+            lines.push(format!("dX {:+.0} px  dY {:+.0} px", dx_px, dy_px));
+            lines.push(format!(
+                "Yaw {:+.2} deg  Pitch {:+.2} deg",
+                yaw_deg, pitch_deg
+            ));
+        }
+
+        let origin = style.point(config.text_position);
+        let line_height = style.px(40);
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == 0 { state_color } else { reticle };
+            imgproc::put_text(
+                frame,
+                line,
+                Point::new(origin.x, origin.y + line_height * i as i32),
+                imgproc::FONT_HERSHEY_SIMPLEX,
+                style.font_scale * 0.8,
+                color,
+                style.thickness,
+                imgproc::LINE_AA,
+                false,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Уголки вокруг рамки захваченной цели.
+fn draw_brackets(
+    frame: &mut Mat,
+    bbox: Rect,
+    color: opencv::core::Scalar,
+    thickness: i32,
+) -> opencv::Result<()> {
+    let len = (bbox.width.min(bbox.height) / 4).max(4);
+    let (x0, y0, x1, y1) = (bbox.x, bbox.y, bbox.x + bbox.width, bbox.y + bbox.height);
+
+    for (corner, sx, sy) in [
+        (Point::new(x0, y0), 1, 1),
+        (Point::new(x1, y0), -1, 1),
+        (Point::new(x0, y1), 1, -1),
+        (Point::new(x1, y1), -1, -1),
+    ] {
+        imgproc::line(
+            frame,
+            corner,
+            Point::new(corner.x + sx * len, corner.y),
+            color,
+            thickness,
+            imgproc::LINE_8,
+            0,
+        )?;
+        imgproc::line(
+            frame,
+            corner,
+            Point::new(corner.x, corner.y + sy * len),
+            color,
+            thickness,
+            imgproc::LINE_8,
+            0,
+        )?;
+    }
+    Ok(())
+}
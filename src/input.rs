@@ -0,0 +1,606 @@
+use crate::config::{InputBackend, InputConfig};
+use crate::utils::{BBox, iou};
+use log::{info, warn};
+use opencv::core::{Point, Rect};
+use opencv::highgui;
+use opencv::prelude::*;
+use std::fs::{self, File};
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+const WINDOW: &str = "nano_plus_gstreamer";
+
+// Коды из linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_MT_POSITION_X: u16 = 0x35;
+const ABS_MT_POSITION_Y: u16 = 0x36;
+const BTN_LEFT: u16 = 0x110;
+const BTN_TOUCH: u16 = 0x14a;
+const KEY_ESC: u16 = 1;
+const KEY_BACKSPACE: u16 = 14;
+const KEY_TAB: u16 = 15;
+const KEY_P: u16 = 25;
+const KEY_N: u16 = 49;
+const KEY_LEFT: u16 = 105;
+const KEY_RIGHT: u16 = 106;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Курсор без нажатия, координаты кадра
+    Hover(Point),
+    /// Рамка, которую оператор сейчас тянет
+    Dragging(Rect),
+    Click(Point),
+    /// Отпущенная рамка
+    Select(Rect),
+    Next,
+    Prev,
+    Cancel,
+}
+
+/// Превращает нажатие, перемещение и отпускание в клик или рамку.
+struct Gesture {
+    min_drag: i32,
+    start: Option<Point>,
+}
+
+impl Gesture {
+    fn new(min_drag: i32) -> Self {
+        Self {
+            min_drag,
+            start: None,
+        }
+    }
+
+    fn drag_rect(&self, start: Point, end: Point) -> Option<Rect> {
+        let (w, h) = ((end.x - start.x).abs(), (end.y - start.y).abs());
+        (w >= self.min_drag && h >= self.min_drag)
+            .then(|| Rect::new(start.x.min(end.x), start.y.min(end.y), w, h))
+    }
+
+    fn press(&mut self, at: Point) {
+        self.start = Some(at);
+    }
+
+    fn moved(&self, at: Point) -> InputEvent {
+        match self.start.and_then(|start| self.drag_rect(start, at)) {
+            Some(rect) => InputEvent::Dragging(rect),
+            None => InputEvent::Hover(at),
+        }
+    }
+
+    fn release(&mut self, at: Point) -> Option<InputEvent> {
+        let start = self.start.take()?;
+        Some(match self.drag_rect(start, at) {
+            Some(rect) => InputEvent::Select(rect),
+            None => InputEvent::Click(at),
+        })
+    }
+}
+
+fn evdev_key(code: u16) -> Option<InputEvent> {
+    match code {
+        KEY_TAB | KEY_RIGHT | KEY_N => Some(InputEvent::Next),
+        KEY_LEFT | KEY_P => Some(InputEvent::Prev),
+        KEY_ESC | KEY_BACKSPACE => Some(InputEvent::Cancel),
+        _ => None,
+    }
+}
+
+fn highgui_key(key: i32) -> Option<InputEvent> {
+    // Стрелки от wait_key_ex в GTK и Qt
+    match key {
+        9 | 0x6e | 0xff53 | 0x27_0000 => Some(InputEvent::Next),
+        0x70 | 0xff51 | 0x25_0000 => Some(InputEvent::Prev),
+        27 | 8 => Some(InputEvent::Cancel),
+        _ => None,
+    }
+}
+
+/// Номер ioctl `_IOR('E', 0x40 + axis, struct input_absinfo)`.
+fn eviocgabs(axis: u16) -> u64 {
+    (2u64 << 30)
+        | ((std::mem::size_of::<libc::input_absinfo>() as u64) << 16)
+        | ((b'E' as u64) << 8)
+        | (0x40 + axis as u64)
+}
+
+/// Диапазон абсолютной оси из EVIOCGABS.
+fn abs_range(file: &File, axis: u16) -> Option<(i32, i32)> {
+    let mut info: libc::input_absinfo = unsafe { std::mem::zeroed() };
+    // SAFETY: ядро заполняет структуру нужного размера
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), eviocgabs(axis) as _, &mut info) };
+    (result == 0 && info.maximum > info.minimum).then_some((info.minimum, info.maximum))
+}
+
+/// Состояние одного устройства evdev: курсор в координатах кадра и жест.
+struct Device {
+    width: i32,
+    height: i32,
+    x_range: Option<(i32, i32)>,
+    y_range: Option<(i32, i32)>,
+    cursor: Point,
+    moved: bool,
+    button: Option<bool>,
+    gesture: Gesture,
+}
+
+impl Device {
+    fn scale(value: i32, range: Option<(i32, i32)>, size: i32) -> i32 {
+        match range {
+            Some((min, max)) => ((value - min) as i64 * size as i64 / (max - min) as i64) as i32,
+            None => value,
+        }
+        .clamp(0, size - 1)
+    }
+
+    fn handle(&mut self, kind: u16, code: u16, value: i32) -> Option<InputEvent> {
+        match (kind, code) {
+            (EV_ABS, ABS_X | ABS_MT_POSITION_X) => {
+                self.cursor.x = Self::scale(value, self.x_range, self.width);
+                self.moved = true;
+            }
+            (EV_ABS, ABS_Y | ABS_MT_POSITION_Y) => {
+                self.cursor.y = Self::scale(value, self.y_range, self.height);
+                self.moved = true;
+            }
+            (EV_REL, REL_X) => {
+                self.cursor.x = (self.cursor.x + value).clamp(0, self.width - 1);
+                self.moved = true;
+            }
+            (EV_REL, REL_Y) => {
+                self.cursor.y = (self.cursor.y + value).clamp(0, self.height - 1);
+                self.moved = true;
+            }
+            (EV_KEY, BTN_LEFT | BTN_TOUCH) => self.button = Some(value != 0),
+            // 1 — нажатие, 2 — автоповтор
+            (EV_KEY, code) if value == 1 => return evdev_key(code),
+            // Координаты и кнопка в одном отчёте применяются вместе по SYN_REPORT
+            (EV_SYN, SYN_REPORT) => {
+                if self.button == Some(true) {
+                    self.gesture.press(self.cursor);
+                }
+                let event = match self.button.take() {
+                    Some(false) => self.gesture.release(self.cursor),
+                    _ if self.moved => Some(self.gesture.moved(self.cursor)),
+                    _ => None,
+                };
+                self.moved = false;
+                return event;
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+fn read_device(path: PathBuf, mut device: Device, tx: Sender<InputEvent>) {
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            warn!("Can't open input device {}: {}", path.display(), err);
+            return;
+        }
+    };
+    device.x_range = abs_range(&file, ABS_MT_POSITION_X).or_else(|| abs_range(&file, ABS_X));
+    device.y_range = abs_range(&file, ABS_MT_POSITION_Y).or_else(|| abs_range(&file, ABS_Y));
+    info!("Input device {}", path.display());
+
+    let mut buf = [0u8; std::mem::size_of::<libc::input_event>()];
+    while file.read_exact(&mut buf).is_ok() {
+        // SAFETY: буфер ровно размера input_event, чтение без выравнивания
+        let event: libc::input_event = unsafe { std::ptr::read_unaligned(buf.as_ptr().cast()) };
+        if let Some(event) = device.handle(event.type_, event.code, event.value)
+            && tx.send(event).is_err()
+        {
+            return;
+        }
+    }
+    warn!("Input device {} closed", path.display());
+}
+
+/// Ввод оператора: события из evdev (для kmssink) или из окна highgui.
+pub struct Input {
+    rx: Receiver<InputEvent>,
+    highgui: bool,
+}
+
+impl Input {
+    /// `None`, если ввод выключен в конфиге.
+    pub fn start(config: &InputConfig, width: i32, height: i32) -> Option<Self> {
+        let (tx, rx) = mpsc::channel();
+
+        match config.backend {
+            InputBackend::None => return None,
+            InputBackend::Evdev => {
+                let devices = if config.devices.is_empty() {
+                    event_devices(Path::new("/dev/input"))
+                } else {
+                    config.devices.iter().map(PathBuf::from).collect()
+                };
+                for path in devices {
+                    let device = Device {
+                        width,
+                        height,
+                        x_range: None,
+                        y_range: None,
+                        cursor: Point::new(width / 2, height / 2),
+                        moved: false,
+                        button: None,
+                        gesture: Gesture::new(config.min_drag_px),
+                    };
+                    let tx = tx.clone();
+                    std::thread::spawn(move || read_device(path, device, tx));
+                }
+            }
+            InputBackend::Highgui => {
+                highgui::named_window(WINDOW, highgui::WINDOW_AUTOSIZE)
+                    .expect("Can't create window");
+                let mut gesture = Gesture::new(config.min_drag_px);
+                highgui::set_mouse_callback(
+                    WINDOW,
+                    Some(Box::new(move |event, x, y, _flags| {
+                        let at = Point::new(x, y);
+                        let event = match event {
+                            highgui::EVENT_LBUTTONDOWN => {
+                                gesture.press(at);
+                                None
+                            }
+                            highgui::EVENT_MOUSEMOVE => Some(gesture.moved(at)),
+                            highgui::EVENT_LBUTTONUP => gesture.release(at),
+                            _ => None,
+                        };
+                        if let Some(event) = event {
+                            let _ = tx.send(event);
+                        }
+                    })),
+                )
+                .expect("Can't set mouse callback");
+            }
+        }
+
+        Some(Self {
+            rx,
+            highgui: config.backend == InputBackend::Highgui,
+        })
+    }
+
+    /// Все события с прошлого кадра. Для highgui заодно обрабатывает клавиатуру.
+    pub fn poll(&mut self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        if self.highgui {
+            // wait_key крутит цикл событий окна, без него не придут и клики
+            if let Some(event) = highgui::wait_key_ex(1).ok().and_then(highgui_key) {
+                events.push(event);
+            }
+        }
+        events.extend(self.rx.try_iter());
+        events
+    }
+
+    /// Показывает кадр в окне highgui; для evdev ничего не делает.
+    pub fn show(&self, frame: &Mat) {
+        if self.highgui
+            && let Err(err) = highgui::imshow(WINDOW, frame)
+        {
+            warn!("Can't show frame: {}", err);
+        }
+    }
+}
+
+fn event_devices(dir: &Path) -> Vec<PathBuf> {
+    let mut devices: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("event"))
+        })
+        .collect();
+    devices.sort();
+    devices
+}
+
+/// Решение оператора.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Lock(Rect),
+    Release,
+}
+
+/// Нужны ли для события детекции текущего кадра.
+pub fn needs_detections(event: &InputEvent) -> bool {
+    matches!(
+        event,
+        InputEvent::Click(_) | InputEvent::Next | InputEvent::Prev
+    )
+}
+
+/// Переводит событие в захват или сброс цели.
+///
+/// Клик выбирает самую маленькую детекцию под курсором, рамка берётся как есть,
+/// `Next`/`Prev` перебирают детекции слева направо начиная с текущей цели.
+pub fn resolve(
+    event: &InputEvent,
+    detections: &[BBox],
+    current: Option<Rect>,
+) -> Option<Selection> {
+    match *event {
+        InputEvent::Click(at) => detections
+            .iter()
+            .map(BBox::rect)
+            .filter(|rect| rect.contains(at))
+            .min_by_key(|rect| rect.area())
+            .map(Selection::Lock),
+        InputEvent::Select(rect) if rect.width > 0 && rect.height > 0 => {
+            Some(Selection::Lock(rect))
+        }
+        InputEvent::Next | InputEvent::Prev => {
+            let mut rects: Vec<Rect> = detections.iter().map(BBox::rect).collect();
+            if rects.is_empty() {
+                return None;
+            }
+            rects.sort_by_key(|rect| rect.x + rect.width / 2);
+
+            let n = rects.len();
+            let index = current.and_then(|current| {
+                rects
+                    .iter()
+                    .map(|rect| iou(&current, rect))
+                    .enumerate()
+                    .filter(|(_, overlap)| *overlap > 0.0)
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(i, _)| i)
+            });
+            let next = match (event, index) {
+                (InputEvent::Next, Some(i)) => (i + 1) % n,
+                (InputEvent::Next, None) => 0,
+                (_, Some(i)) => (i + n - 1) % n,
+                (_, None) => n - 1,
+            };
+            Some(Selection::Lock(rects[next]))
+        }
+        InputEvent::Cancel => Some(Selection::Release),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bbox(x: f32, y: f32, w: f32, h: f32) -> BBox {
+        BBox {
+            x1: x,
+            y1: y,
+            x2: x + w,
+            y2: y + h,
+            class_id: 0,
+            confidence: 0.9,
+        }
+    }
+
+    fn device() -> Device {
+        Device {
+            width: 640,
+            height: 480,
+            x_range: None,
+            y_range: None,
+            cursor: Point::new(0, 0),
+            moved: false,
+            button: None,
+            gesture: Gesture::new(10),
+        }
+    }
+
+    /// Один отчёт evdev: события и завершающий SYN_REPORT.
+    fn report(device: &mut Device, events: &[(u16, u16, i32)]) -> Option<InputEvent> {
+        for &(kind, code, value) in events {
+            assert_eq!(device.handle(kind, code, value), None);
+        }
+        device.handle(EV_SYN, SYN_REPORT, 0)
+    }
+
+    #[test]
+    fn eviocgabs_matches_kernel_headers() {
+        // EVIOCGABS(ABS_X) и EVIOCGABS(ABS_MT_POSITION_X) из linux/input.h
+        assert_eq!(eviocgabs(ABS_X), 0x8018_4540);
+        assert_eq!(eviocgabs(ABS_MT_POSITION_X), 0x8018_4575);
+    }
+
+    #[test]
+    fn click_picks_smallest_box_under_cursor() {
+        let detections = [
+            bbox(0.0, 0.0, 400.0, 400.0),
+            bbox(100.0, 100.0, 50.0, 50.0),
+            bbox(90.0, 90.0, 100.0, 100.0),
+            bbox(300.0, 300.0, 20.0, 20.0),
+        ];
+        let click = |x, y| resolve(&InputEvent::Click(Point::new(x, y)), &detections, None);
+        assert_eq!(
+            click(120, 120),
+            Some(Selection::Lock(Rect::new(100, 100, 50, 50)))
+        );
+        assert_eq!(
+            click(180, 180),
+            Some(Selection::Lock(Rect::new(90, 90, 100, 100)))
+        );
+        assert_eq!(click(500, 450), None);
+    }
+
+    #[test]
+    fn next_and_prev_wrap_around() {
+        // Детекции не по порядку: перебор идёт слева направо по центрам
+        let detections = [
+            bbox(400.0, 0.0, 50.0, 50.0),
+            bbox(0.0, 0.0, 50.0, 50.0),
+            bbox(200.0, 0.0, 50.0, 50.0),
+        ];
+        let (left, middle, right) = (
+            Rect::new(0, 0, 50, 50),
+            Rect::new(200, 0, 50, 50),
+            Rect::new(400, 0, 50, 50),
+        );
+        let step = |event, current| resolve(&event, &detections, current);
+
+        assert_eq!(step(InputEvent::Next, None), Some(Selection::Lock(left)));
+        assert_eq!(step(InputEvent::Prev, None), Some(Selection::Lock(right)));
+        assert_eq!(
+            step(InputEvent::Next, Some(left)),
+            Some(Selection::Lock(middle))
+        );
+        assert_eq!(
+            step(InputEvent::Next, Some(right)),
+            Some(Selection::Lock(left))
+        );
+        assert_eq!(
+            step(InputEvent::Prev, Some(left)),
+            Some(Selection::Lock(right))
+        );
+        // Текущая цель немного уехала от своей детекции
+        assert_eq!(
+            step(InputEvent::Prev, Some(Rect::new(205, 5, 50, 50))),
+            Some(Selection::Lock(left))
+        );
+        assert_eq!(resolve(&InputEvent::Next, &[], Some(left)), None);
+    }
+
+    #[test]
+    fn select_and_cancel() {
+        let rect = Rect::new(10, 20, 30, 40);
+        assert_eq!(
+            resolve(&InputEvent::Select(rect), &[], None),
+            Some(Selection::Lock(rect))
+        );
+        assert_eq!(
+            resolve(&InputEvent::Select(Rect::new(10, 20, 0, 40)), &[], None),
+            None
+        );
+        assert_eq!(
+            resolve(
+                &InputEvent::Cancel,
+                &[bbox(0.0, 0.0, 10.0, 10.0)],
+                Some(rect)
+            ),
+            Some(Selection::Release)
+        );
+        assert_eq!(
+            resolve(&InputEvent::Hover(Point::new(1, 1)), &[], None),
+            None
+        );
+    }
+
+    #[test]
+    fn gesture_click_or_drag() {
+        let mut gesture = Gesture::new(10);
+        assert_eq!(gesture.release(Point::new(5, 5)), None);
+
+        gesture.press(Point::new(100, 100));
+        assert_eq!(
+            gesture.moved(Point::new(105, 130)),
+            InputEvent::Hover(Point::new(105, 130))
+        );
+        assert_eq!(
+            gesture.release(Point::new(108, 92)),
+            Some(InputEvent::Click(Point::new(108, 92)))
+        );
+
+        // Рамку можно тянуть в любую сторону
+        gesture.press(Point::new(100, 100));
+        assert_eq!(
+            gesture.moved(Point::new(60, 150)),
+            InputEvent::Dragging(Rect::new(60, 100, 40, 50))
+        );
+        assert_eq!(
+            gesture.release(Point::new(50, 40)),
+            Some(InputEvent::Select(Rect::new(50, 40, 50, 60)))
+        );
+        assert_eq!(
+            gesture.moved(Point::new(10, 10)),
+            InputEvent::Hover(Point::new(10, 10))
+        );
+    }
+
+    #[test]
+    fn device_drag_from_touchscreen() {
+        let mut device = Device {
+            x_range: Some((0, 4095)),
+            y_range: Some((0, 4095)),
+            ..device()
+        };
+        // Касание и координаты приходят в одном отчёте
+        assert_eq!(
+            report(
+                &mut device,
+                &[
+                    (EV_KEY, BTN_TOUCH, 1),
+                    (EV_ABS, ABS_MT_POSITION_X, 1024),
+                    (EV_ABS, ABS_MT_POSITION_Y, 1024)
+                ]
+            ),
+            Some(InputEvent::Hover(Point::new(160, 120)))
+        );
+        assert_eq!(
+            report(
+                &mut device,
+                &[
+                    (EV_ABS, ABS_MT_POSITION_X, 2048),
+                    (EV_ABS, ABS_MT_POSITION_Y, 2048)
+                ]
+            ),
+            Some(InputEvent::Dragging(Rect::new(160, 120, 160, 120)))
+        );
+        assert_eq!(
+            report(&mut device, &[(EV_ABS, ABS_MT_POSITION_Y, 4095)]),
+            Some(InputEvent::Dragging(Rect::new(160, 120, 160, 359)))
+        );
+        assert_eq!(
+            report(&mut device, &[(EV_KEY, BTN_TOUCH, 0)]),
+            Some(InputEvent::Select(Rect::new(160, 120, 160, 359)))
+        );
+        assert_eq!(report(&mut device, &[]), None);
+    }
+
+    #[test]
+    fn device_mouse_click_and_keys() {
+        let mut device = device();
+        assert_eq!(
+            report(&mut device, &[(EV_REL, REL_X, 50), (EV_REL, REL_Y, -20)]),
+            Some(InputEvent::Hover(Point::new(50, 0)))
+        );
+        assert_eq!(
+            report(&mut device, &[(EV_REL, REL_X, 1000), (EV_REL, REL_Y, 30)]),
+            Some(InputEvent::Hover(Point::new(639, 30)))
+        );
+        assert_eq!(report(&mut device, &[(EV_KEY, BTN_LEFT, 1)]), None);
+        assert_eq!(
+            report(&mut device, &[(EV_REL, REL_X, -5)]),
+            Some(InputEvent::Hover(Point::new(634, 30)))
+        );
+        assert_eq!(
+            report(&mut device, &[(EV_KEY, BTN_LEFT, 0)]),
+            Some(InputEvent::Click(Point::new(634, 30)))
+        );
+
+        // Клавиши срабатывают на нажатие, автоповтор и отпускание игнорируются
+        assert_eq!(device.handle(EV_KEY, KEY_TAB, 1), Some(InputEvent::Next));
+        assert_eq!(device.handle(EV_KEY, KEY_LEFT, 1), Some(InputEvent::Prev));
+        assert_eq!(device.handle(EV_KEY, KEY_ESC, 1), Some(InputEvent::Cancel));
+        assert_eq!(device.handle(EV_KEY, KEY_ESC, 2), None);
+        assert_eq!(device.handle(EV_KEY, KEY_ESC, 0), None);
+        assert_eq!(device.handle(EV_SYN, SYN_REPORT, 0), None);
+    }
+}
@@ -1,13 +1,16 @@
+use crate::config::TrackerKind;
+use crate::trackers::{TrackResult, Tracker};
 use opencv::core::{Ptr, Rect};
 use opencv::tracking::{TrackerKCF, TrackerKCF_Params};
 use opencv::prelude::*;
+use ticky::Stopwatch;
 
 pub struct KcfTracker {
     tracker: Ptr<TrackerKCF>
 }
 
 impl KcfTracker {
-    pub fn new(initial_bbox: Rect, frame: &Mat) -> opencv::Result<Self> {
+    pub fn new() -> opencv::Result<Self> {
         let params = TrackerKCF_Params {
             detect_thresh: 0.07,       // 0.5
             sigma: 1.043590774305246,  // 0.2
@@ -24,15 +27,27 @@ impl KcfTracker {
             desc_pca: 2,               // 2
             desc_npca: 1,              // 1
         };
-        // let def_param = TrackerKCF_Params::default()?;
-        let mut tracker = TrackerKCF::create(params)?;
-        tracker.init(frame, initial_bbox)?;
+        let tracker = TrackerKCF::create(params)?;
         Ok(Self { tracker })
     }
+}
+
+impl Tracker for KcfTracker {
+    fn init(&mut self, frame: &Mat, bbox: Rect) -> opencv::Result<()> {
+        self.tracker.init(frame, bbox)
+    }
 
-    pub fn update(&mut self, frame: &Mat) -> opencv::Result<Option<Rect>> {
+    // KCF не отдаёт уверенность, поэтому успешное обновление считается 1.0
+    fn update(&mut self, frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+        let mut sw = Stopwatch::start_new();
         let mut bbox = Rect::default();
-        let ok = self.tracker.update(&frame, &mut bbox)?;
-        if ok { Ok(Some(bbox)) } else { Ok(None) }
+        let ok = self.tracker.update(frame, &mut bbox)?;
+        sw.stop();
+        Ok(ok.then_some(TrackResult {
+            bbox,
+            score: 1.0,
+            source: TrackerKind::Kcf,
+            inference_time: sw.elapsed,
+        }))
     }
-}
\ No newline at end of file
+}
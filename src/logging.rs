@@ -0,0 +1,194 @@
+use crate::config::{LogFormat, LoggingConfig};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Переопределяет `[logging] level`, формат как у `RUST_LOG`:
+/// `info,nano_plus_gstreamer::fallback_tracker=debug`.
+pub const LOG_ENV: &str = "NANO_LOG";
+/// Переопределяет `[logging] format`: `text` или `json`.
+pub const LOG_FORMAT_ENV: &str = "NANO_LOG_FORMAT";
+
+thread_local! {
+    static SPANS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Logger {
+    default: LevelFilter,
+    /// Уровни по префиксу модуля, самый длинный префикс — первым
+    modules: Vec<(String, LevelFilter)>,
+    format: LogFormat,
+}
+
+impl Logger {
+    fn parse(spec: &str, format: LogFormat) -> Self {
+        let mut default = LevelFilter::Info;
+        let mut modules = Vec::new();
+
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((module, level)) => match level.trim().parse() {
+                    Ok(level) => modules.push((module.trim().to_string(), level)),
+                    Err(_) => eprintln!("Unknown log level in '{}'", part),
+                },
+                None => match part.parse() {
+                    Ok(level) => default = level,
+                    Err(_) => eprintln!("Unknown log level in '{}'", part),
+                },
+            }
+        }
+        modules.sort_by_key(|(module, _)| Reverse(module.len()));
+
+        Self { default, modules, format }
+    }
+
+    /// Модуль совпадает целиком или как путь-предок: `a::b` задаёт уровень для `a::b::c`, но не для `a::bc`.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        let spans = SPANS.with(|spans| spans.borrow().join(":"));
+
+        let mut line = String::new();
+        match self.format {
+            LogFormat::Text => {
+                let _ = write!(line, "{:.3} {:<5} {}", timestamp, record.level(), record.target());
+                if !spans.is_empty() {
+                    let _ = write!(line, " [{}]", spans);
+                }
+                let _ = write!(line, ": {}", record.args());
+            }
+            LogFormat::Json => {
+                let _ = write!(
+                    line,
+                    "{{\"ts\":{:.3},\"level\":\"{}\",\"target\":\"{}\"",
+                    timestamp,
+                    record.level(),
+                    escape_json(record.target())
+                );
+                if !spans.is_empty() {
+                    let _ = write!(line, ",\"span\":\"{}\"", escape_json(&spans));
+                }
+                let _ = write!(line, ",\"message\":\"{}\"}}", escape_json(&record.args().to_string()));
+            }
+        }
+
+        // Всё, что важнее info, — в stderr, чтобы не терялось среди телеметрии
+        if record.level() <= Level::Warn {
+            let _ = writeln!(std::io::stderr().lock(), "{}", line);
+        } else {
+            let _ = writeln!(std::io::stdout().lock(), "{}", line);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+pub fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Настраивает логгер из конфига; переменные окружения имеют приоритет.
+pub fn init(config: &LoggingConfig) {
+    let spec = std::env::var(LOG_ENV).unwrap_or_else(|_| config.level.clone());
+    let format = match std::env::var(LOG_FORMAT_ENV).as_deref() {
+        Ok("json") => LogFormat::Json,
+        Ok("text") => LogFormat::Text,
+        _ => config.format,
+    };
+
+    let logger = Logger::parse(&spec, format);
+    log::set_max_level(logger.max_level());
+    if log::set_boxed_logger(Box::new(logger)).is_err() {
+        eprintln!("Logger is already initialized");
+    }
+}
+
+/// Именованный участок работы. Пока guard жив, имя попадает в каждую
+/// запись лога этого потока; при выходе время участка пишется на уровне trace.
+pub struct Span {
+    name: &'static str,
+    start: Instant,
+}
+
+pub fn span(name: &'static str) -> Span {
+    SPANS.with(|spans| spans.borrow_mut().push(name));
+    Span { name, start: Instant::now() }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        log::trace!(
+            target: "nano_plus_gstreamer::span",
+            "{} took {:.2} ms",
+            self.name,
+            self.start.elapsed().as_secs_f64() * 1000.0
+        );
+        SPANS.with(|spans| {
+            spans.borrow_mut().pop();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_levels() {
+        let logger = Logger::parse("warn, app::trackers=debug, app::trackers::vit=trace, app=info", LogFormat::Text);
+        assert_eq!(logger.level_for("app::trackers"), LevelFilter::Debug);
+        assert_eq!(logger.level_for("app::trackers::vit"), LevelFilter::Trace);
+        assert_eq!(logger.level_for("app::trackers::nano"), LevelFilter::Debug);
+        // Соседний модуль с тем же началом имени — не потомок
+        assert_eq!(logger.level_for("app::trackers_extra"), LevelFilter::Info);
+        assert_eq!(logger.level_for("app::trackers::vitx"), LevelFilter::Debug);
+        assert_eq!(logger.level_for("application"), LevelFilter::Warn);
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+    }
+}
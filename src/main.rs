@@ -1,33 +1,112 @@
+mod camera;
+mod config;
+mod control;
+mod detector_eval;
+mod dnn_backend;
+mod eval;
+mod fallback_tracker;
+mod gimbal;
+mod hud;
+mod input;
 mod kcftracker;
+mod logging;
+mod mavlink;
+mod metrics;
+mod overlay;
+mod pelco_d;
+mod profiler;
+mod recording;
+#[cfg(test)]
+mod regression;
+mod sbgc;
+mod serial;
+mod sysmon;
+mod telemetry;
+mod template_refresh;
+mod thermal;
 mod trackers;
 mod utils;
-mod yolo;
 mod vit_tracker;
-mod vit_with_dasiam_trackers;
+mod yolo;
 
-use crate::trackers::NanoTrack;
-use crate::utils::{center_crop, expand_roi, expand_roi_rect, get_cpu_temp, get_cpu_usage, get_mem_usage, iou, mat_to_ndarray};
+use crate::camera::Camera;
+use crate::config::{AppConfig, InputBackend, TrackersConfig};
+use crate::control::{Command, ControlServer, Event, LockOrigin, Request, Status};
+use crate::gimbal::Gimbal;
+use crate::input::{Input, InputEvent, Selection, needs_detections, resolve};
+use crate::mavlink::MavlinkEmitter;
+use crate::metrics::Metrics;
+use crate::overlay::{OverlayFrame, OverlayRenderer, state_text};
+use crate::profiler::{Profiler, Stage};
+use crate::recording::{Recorder, Replay};
+use crate::telemetry::{Latency, Record, Telemetry, TrackState};
+use crate::template_refresh::TemplateRefresh;
+use crate::thermal::{ThermalGovernor, Throttle};
+use crate::trackers::{TrackResult, Tracker, create_tracker};
+use crate::utils::{BBox, iou};
 use crate::yolo::YoloV8;
 use gstreamer::Pipeline;
 use gstreamer::prelude::*;
-use opencv::core::{Rect, Scalar};
+use log::{debug, error, info, warn};
+use opencv::core;
+use opencv::core::{Point, Rect};
 use opencv::prelude::*;
-use opencv::{core, imgproc};
+use serde_json::{Value, json};
 use std::os::raw::c_void;
-use crate::vit_tracker::VitTracker;
-use crate::vit_with_dasiam_trackers::VitWithDaSiamTracker;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 fn main() -> opencv::Result<()> {
     gstreamer::init().unwrap();
+    let mut config = AppConfig::load();
+    logging::init(&config.logging);
+    config.trackers = dnn_backend::probe_trackers(&config.trackers);
+
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("eval") => return eval::main(&config.trackers, &args[2..]),
+        Some("eval-detector") => return detector_eval::main(&config.detector, &args[2..]),
+        _ => {}
+    }
 
-    let pipeline_in_str = concat!(
-        "libcamerasrc ! ",
-        "videoconvert ! ",
-        "video/x-raw,format=BGR,width=1632,height=1232,framerate=10/1 ! ",
-        "appsink name=sink sync=false max-buffers=1 drop=true"
-    );
+    // `replay <video> [annotations]` — вход из записи вместо камеры
+    let mut replay: Option<Replay> = None;
+    let mut sidecar: Option<PathBuf> = None;
+    let pipeline_in_str = if args.get(1).map(String::as_str) == Some("replay") {
+        let video = PathBuf::from(args.get(2).expect("Usage: replay <video> [annotations]"));
+        let annotations = args
+            .get(3)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| recording::sidecar_path(&video));
+        replay = Some(Replay::load(&annotations).expect("Can't load annotations"));
+        sidecar = Some(video.with_extension("replay.jsonl"));
+        // Воспроизводятся только записанные команды
+        config.input.backend = InputBackend::None;
+        config.control.enabled = false;
+        config.thermal.enabled = false;
+        recording::replay_pipeline(&video)
+    } else {
+        let caps = "video/x-raw,format=BGR,width=1632,height=1232,framerate=10/1";
+        let appsink = "appsink name=sink sync=false max-buffers=1 drop=true";
+        if config.recording.enabled {
+            let (video, annotations) = recording::session_paths(&config.recording)
+                .expect("Can't create recording directory");
+            info!("Recording input to {}", video.display());
+            sidecar = Some(annotations);
+            format!(
+                "libcamerasrc ! videoconvert ! {} ! tee name=rec ! queue ! {} {}",
+                caps,
+                appsink,
+                recording::tee_branch(&config.recording, &video)
+            )
+        } else {
+            format!("libcamerasrc ! videoconvert ! {} ! {}", caps, appsink)
+        }
+    };
 
-    let pipeline_in = gstreamer::parse::launch(pipeline_in_str).expect("Can't launch pipeline");
+    let pipeline_in = gstreamer::parse::launch(&pipeline_in_str).expect("Can't launch pipeline");
     let pipeline_in = pipeline_in
         .dynamic_cast::<Pipeline>()
         .expect("Couldn't cast pipeline to pipeline");
@@ -77,7 +156,7 @@ fn main() -> opencv::Result<()> {
                 use gstreamer::MessageView;
                 match msg.view() {
                     MessageView::Error(err) => {
-                        eprintln!(
+                        error!(
                             "Pipeline (in) error from {:?}: {} ({:?})",
                             err.src().map(|s| s.path_string()),
                             err.error(),
@@ -86,11 +165,11 @@ fn main() -> opencv::Result<()> {
                         break;
                     }
                     MessageView::Eos(_) => {
-                        eprintln!("Pipeline (in) EOS");
+                        info!("Pipeline (in) EOS");
                         break;
                     }
                     MessageView::StateChanged(changed) => {
-                        eprintln!("Pipeline (in) state changed: {:?}", changed);
+                        debug!("Pipeline (in) state changed: {:?}", changed);
                         break;
                     }
                     _ => {}
@@ -99,26 +178,109 @@ fn main() -> opencv::Result<()> {
         });
     }
 
+    let system = sysmon::spawn_sampler(
+        Duration::from_secs_f64(config.sysmon.interval_secs.max(0.1)),
+        &config.thermal,
+    );
+    let metrics = Arc::new(Metrics::new(system.clone()));
+    if config.metrics.enabled {
+        if let Err(err) = metrics::serve(&config.metrics.listen, metrics.clone()) {
+            error!(
+                "Can't start metrics server on {}: {}",
+                config.metrics.listen, err
+            );
+        }
+    }
+
+    let main_loop = gstreamer::glib::MainLoop::new(None, false);
+    let main_loop_thread = main_loop.clone();
     let appsink_thread = appsink.clone();
     let appsrc_thread = appsrc.clone();
 
     std::thread::spawn(move || {
-        let mut yolo = YoloV8::new().unwrap();
-        let mut nano_track: Option<VitTracker> = None;
+        let mut yolo = YoloV8::new(&config.detector).unwrap();
+        if let Some(size) = yolo.input_size {
+            fit_static_input(&mut config, size);
+        }
+        let mut nano_track: Option<Box<dyn Tracker>> = None;
         let mut last_bbox: Option<Rect> = None;
+        let mut template_refresh = TemplateRefresh::new(&config.template_refresh);
+        let mut profiler = Profiler::new(&config.profiling);
+        let mut acquired_once = false;
+        let mut thermal = ThermalGovernor::new(&config.thermal);
+        let mut throttle = Throttle::default();
+        let mut frame_index: u64 = 0;
+        let mut overlay = OverlayRenderer::new(&config.overlay, Camera::new(&config.camera));
+        let mut track_id: u64 = 0;
+        let mut input = Input::start(&config.input, width, height);
+        let mut cursor: Option<Point> = None;
+        let mut dragging: Option<Rect> = None;
+        // Оператор сбросил цель — автозахват не должен сразу взять новую
+        let mut operator_hold = false;
+        let control = if config.control.enabled {
+            match ControlServer::start(&config.control.socket) {
+                Ok(server) => Some(server),
+                Err(err) => {
+                    error!(
+                        "Can't start control socket {}: {}",
+                        config.control.socket, err
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        // Состояние прошлого кадра для `status` и `lock_detection`
+        let mut last_detections: Vec<BBox> = Vec::new();
+        let mut last_target: Option<TrackResult> = None;
+        let mut telemetry = Telemetry::new(&config.telemetry);
+        let mut mavlink = MavlinkEmitter::new(&config.mavlink, Camera::new(&config.camera));
+        let mut gimbal = Gimbal::new(&config.gimbal, Camera::new(&config.camera));
+        let mut recorder = sidecar.and_then(|path| {
+            Recorder::create(&path)
+                .map_err(|err| error!("Can't create {}: {}", path.display(), err))
+                .ok()
+        });
         loop {
-            match appsink_thread.try_pull_sample(gstreamer::ClockTime::from_seconds(5)) {
+            let stage_start = Instant::now();
+            let sample = appsink_thread.try_pull_sample(gstreamer::ClockTime::from_seconds(5));
+            profiler.record(Stage::Pull, stage_start);
+            match sample {
+                None if replay
+                    .as_ref()
+                    .is_some_and(|replay| replay.is_done() || appsink_thread.is_eos()) =>
+                {
+                    info!("Replay finished");
+                    main_loop_thread.quit();
+                    break;
+                }
                 None => {
-                    println!("Can't pull sample");
+                    warn!("Can't pull sample");
+                    metrics.frames_dropped.fetch_add(1, Ordering::Relaxed);
                 }
                 Some(sample) => unsafe {
+                    let frame_start = Instant::now();
+                    let stage_start = frame_start;
                     let buffer = match sample.buffer() {
                         None => {
-                            eprintln!("Can't get buffer");
+                            error!("Can't get buffer");
+                            metrics.frames_dropped.fetch_add(1, Ordering::Relaxed);
                             continue;
                         }
                         Some(b) => b,
                     };
+                    let pts = buffer.pts();
+                    let mut replay_frame = match replay.as_mut() {
+                        Some(replay) => match replay.next(pts.map(|pts| pts.nseconds())) {
+                            Some(frame) => Some(frame),
+                            None => {
+                                // При записи этот кадр не обрабатывался
+                                continue;
+                            }
+                        },
+                        None => None,
+                    };
 
                     let caps = sample.caps().expect("Can't get caps");
                     let s = caps.structure(0).expect("Can't get structure");
@@ -128,7 +290,8 @@ fn main() -> opencv::Result<()> {
                     let map = match buffer.map_readable() {
                         Ok(m) => m,
                         Err(err) => {
-                            eprintln!("Can't get map: {}", err);
+                            error!("Can't get map: {}", err);
+                            metrics.frames_dropped.fetch_add(1, Ordering::Relaxed);
                             continue;
                         }
                     };
@@ -144,49 +307,334 @@ fn main() -> opencv::Result<()> {
                     ) {
                         Ok(m) => m,
                         Err(err) => {
-                            eprintln!("Can't get mat: {}", err);
+                            error!("Can't get mat: {}", err);
+                            metrics.frames_dropped.fetch_add(1, Ordering::Relaxed);
                             continue;
                         }
                     };
+                    profiler.record(Stage::Map, stage_start);
 
-                    if let Some(t) = nano_track.as_mut() {
-                        // let roi_rect = match last_bbox {
-                        //     None => { Rect::new(0,0, mat.cols(), mat.rows())}
-                        //     Some(l_bb) => {
-                        //         expand_roi_rect(&mat, l_bb, 100).expect("Expand roi error")
-                        //     }
-                        // };
-
-                        // let crop = Mat::roi(&mat, roi_rect).expect("Can't rotate roi");
-                        if let Ok(bbox) = t.update(&mat) {
-                            if let Some(bbox) = bbox {
-                                // bbox.x += roi_rect.x;
-                                // bbox.y += roi_rect.y;
+                    let new_throttle = match &replay_frame {
+                        Some(frame) => {
+                            frame_index = frame.frame_index;
+                            (frame.thermal_level != throttle.level)
+                                .then(|| thermal.force_level(frame.thermal_level))
+                        }
+                        None => {
+                            frame_index += 1;
+                            // Датчики читает поток sysmon, здесь только его решение
+                            let level = system.thermal_level.load(Ordering::Relaxed);
+                            (level != throttle.level).then(|| thermal.force_level(level))
+                        }
+                    };
+                    if let Some(new_throttle) = new_throttle {
+                        let switch_tracker = new_throttle.tracker != throttle.tracker;
+                        throttle = new_throttle;
+                        metrics
+                            .thermal_level
+                            .store(throttle.level as u64, Ordering::Relaxed);
+                        // Трекер меняется сразу, продолжая с последней рамки
+                        if let (true, Some(_), Some(bbox)) =
+                            (switch_tracker, &nano_track, last_bbox)
+                        {
+                            let trackers = throttle.apply(&config.trackers);
+                            match start_tracker(&trackers, &mat, bbox) {
+                                Ok(tracker) => {
+                                    nano_track = Some(tracker);
+                                    publish(
+                                        &control,
+                                        Event::TrackerSwitched {
+                                            primary: trackers.primary,
+                                            fallback: trackers.fallback,
+                                        },
+                                    );
+                                }
+                                Err(err) => warn!("Can't switch tracker: {}", err),
+                            }
+                        }
+                    }
+                    let input_size = throttle
+                        .detector_input
+                        .unwrap_or(config.detector.input_size);
+                    let mut target: Option<TrackResult> = None;
+                    let mut detections: Option<Vec<BBox>> = None;
+                    let mut detector_time = Duration::ZERO;
+                    let mut tracker_time = Duration::ZERO;
+                    let mut lost = false;
+
+                    let mut selection_events = Vec::new();
+                    for event in input.as_mut().map(Input::poll).unwrap_or_default() {
+                        match event {
+                            InputEvent::Hover(at) => {
+                                cursor = Some(at);
+                                dragging = None;
+                            }
+                            InputEvent::Dragging(rect) => dragging = Some(rect),
+                            event => {
+                                dragging = None;
+                                selection_events.push(event);
+                            }
+                        }
+                    }
 
+                    // Команды кадра от оператора, по управляющему сокету или из записи
+                    // выполняются одинаково, чтобы воспроизведение повторяло живой прогон
+                    let mut commands: Vec<(Command, LockOrigin, Option<Request>)> = Vec::new();
+                    for event in selection_events {
+                        if needs_detections(&event) && detections.is_none() {
+                            detections = Some(detect(
+                                &mut yolo,
+                                &mut profiler,
+                                &metrics,
+                                &mat,
+                                input_size,
+                                &mut detector_time,
+                            ));
+                        }
+                        match resolve(&event, detections.as_deref().unwrap_or_default(), last_bbox)
+                        {
+                            Some(Selection::Lock(rect)) => {
+                                commands.push((Command::Lock(rect), LockOrigin::Operator, None))
+                            }
+                            Some(Selection::Release) => {
+                                commands.push((Command::Release, LockOrigin::Operator, None))
+                            }
+                            None => {}
+                        }
+                    }
+                    for request in control
+                        .as_ref()
+                        .map(ControlServer::poll)
+                        .unwrap_or_default()
+                    {
+                        commands.push((request.command.clone(), LockOrigin::Api, Some(request)));
+                    }
+                    if let Some(frame) = replay_frame.as_mut() {
+                        commands.extend(
+                            frame
+                                .commands
+                                .drain(..)
+                                .map(|(command, origin)| (command, origin, None)),
+                        );
+                    }
+
+                    let mut applied: Vec<(Command, LockOrigin)> = Vec::new();
+                    for (command, origin, request) in commands {
+                        debug!("{} command: {:?}", origin.name(), command);
+                        // В запись попадает команда в том виде, в котором её можно повторить
+                        let mut replayable = Some(command.clone());
+                        let result: Result<Value, String> = match command {
+                            Command::Lock(_) | Command::LockDetection(_) => {
+                                let rect = match command {
+                                    Command::LockDetection(id) => {
+                                        last_detections.get(id).map(BBox::rect)
+                                    }
+                                    Command::Lock(rect) => Some(rect),
+                                    _ => None,
+                                };
+                                match rect.map(|rect| {
+                                    (
+                                        rect,
+                                        start_tracker(
+                                            &throttle.apply(&config.trackers),
+                                            &mat,
+                                            rect,
+                                        ),
+                                    )
+                                }) {
+                                    None => Err("No such detection".to_string()),
+                                    Some((rect, Ok(tracker))) => {
+                                        info!("{} lock: {:?}", origin.name(), rect);
+                                        nano_track = Some(tracker);
+                                        track_id += 1;
+                                        template_refresh.reset();
+                                        last_bbox = Some(rect);
+                                        operator_hold = false;
+                                        replayable = Some(Command::Lock(rect));
+                                        publish(
+                                            &control,
+                                            Event::Locked {
+                                                track_id,
+                                                bbox: rect,
+                                                origin,
+                                            },
+                                        );
+                                        Ok(json!({ "track_id": track_id }))
+                                    }
+                                    Some((_, Err(err))) => {
+                                        Err(format!("Can't init tracker: {}", err))
+                                    }
+                                }
+                            }
+                            Command::Release => {
+                                info!("{} release", origin.name());
+                                nano_track = None;
+                                last_bbox = None;
+                                operator_hold = true;
+                                publish(&control, Event::Released { origin });
+                                Ok(Value::Null)
+                            }
+                            Command::Redetect => {
+                                // Последняя рамка остаётся, чтобы поиск предпочёл ту же цель
+                                if nano_track.take().is_some() {
+                                    publish(&control, Event::Lost { track_id });
+                                }
+                                operator_hold = false;
+                                Ok(Value::Null)
+                            }
+                            Command::SwitchTracker { .. } | Command::SetThresholds { .. } => {
+                                // Настройки меняются только если трекер с ними удалось создать
+                                let mut requested = config.trackers.clone();
+                                match command {
+                                    Command::SwitchTracker { primary, fallback } => {
+                                        requested.primary = primary;
+                                        if let Some(fallback) = fallback {
+                                            requested.fallback = fallback;
+                                        }
+                                    }
+                                    Command::SetThresholds { primary, fallback } => {
+                                        if let Some(primary) = primary {
+                                            requested.primary_threshold = primary;
+                                        }
+                                        if let Some(fallback) = fallback {
+                                            requested.fallback_threshold = fallback;
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                                let trackers = throttle.apply(&requested);
+                                // Пороги и модели задаются при создании, поэтому трекер перезапускается
+                                let started = match (&nano_track, last_bbox) {
+                                    (Some(_), Some(bbox)) => {
+                                        start_tracker(&trackers, &mat, bbox).map(Some)
+                                    }
+                                    _ => create_tracker(&trackers).map(|_| None),
+                                };
+                                match started {
+                                    Ok(tracker) => {
+                                        config.trackers = requested;
+                                        if let Some(tracker) = tracker {
+                                            nano_track = Some(tracker);
+                                        }
+                                        info!(
+                                            "{} trackers: {} / {:?}, thresholds {} / {}",
+                                            origin.name(),
+                                            trackers.primary.name(),
+                                            trackers.fallback.map(|kind| kind.name()),
+                                            trackers.primary_threshold,
+                                            trackers.fallback_threshold
+                                        );
+                                        if matches!(command, Command::SwitchTracker { .. }) {
+                                            publish(
+                                                &control,
+                                                Event::TrackerSwitched {
+                                                    primary: trackers.primary,
+                                                    fallback: trackers.fallback,
+                                                },
+                                            );
+                                        }
+                                        Ok(Value::Null)
+                                    }
+                                    // Текущий трекер продолжает работу со старыми настройками
+                                    Err(err) => Err(format!("Can't create tracker: {}", err)),
+                                }
+                            }
+                            Command::Status => {
+                                replayable = None;
+                                let trackers = throttle.apply(&config.trackers);
+                                let status = Status {
+                                    track_id: nano_track.as_ref().map(|_| track_id),
+                                    target: last_target.as_ref(),
+                                    primary: trackers.primary,
+                                    fallback: trackers.fallback,
+                                    primary_threshold: trackers.primary_threshold,
+                                    fallback_threshold: trackers.fallback_threshold,
+                                    thermal_level: throttle.level,
+                                    detections: &last_detections,
+                                };
+                                Ok(status.to_json())
+                            }
+                        };
+
+                        match (&result, request) {
+                            (Ok(fields), Some(request)) => request.ok(fields),
+                            (Err(err), Some(request)) => request.error(err),
+                            (Err(err), None) => warn!("{} command failed: {}", origin.name(), err),
+                            (Ok(_), None) => {}
+                        }
+                        if let (Some(command), true) = (replayable, result.is_ok()) {
+                            applied.push((command, origin));
+                        }
+                    }
+
+                    if let Some(t) = nano_track.as_mut() {
+                        let _span = logging::span("tracking");
+                        let stage_start = Instant::now();
+                        let result = t.update(&mat);
+                        profiler.record(Stage::TrackerUpdate, stage_start);
+                        tracker_time = stage_start.elapsed();
+                        metrics.tracker_latency.observe(tracker_time);
+                        if let Ok(result) = result {
+                            if let Some(result) = result {
+                                let bbox = result.bbox;
+
+                                debug!(
+                                    "track {}: score {:.2}, inference time: {} ms",
+                                    result.source.name(),
+                                    result.score,
+                                    result.inference_time.as_millis()
+                                );
                                 last_bbox = Some(bbox);
 
-                                imgproc::rectangle(
-                                    &mut mat,
-                                    bbox,
-                                    Scalar::new(0.0, 255., 0., 0.),
-                                    2,
-                                    imgproc::LINE_8,
-                                    0,
-                                )
-                                .unwrap();
+                                // Обновляем шаблон до отрисовки, чтобы рамка не попала в него
+                                if template_refresh.observe(bbox, result.score) {
+                                    let boxes = match detections.take() {
+                                        Some(boxes) => boxes,
+                                        None => detect(
+                                            &mut yolo,
+                                            &mut profiler,
+                                            &metrics,
+                                            &mat,
+                                            input_size,
+                                            &mut detector_time,
+                                        ),
+                                    };
+                                    if template_refresh.confirm(bbox, &boxes) {
+                                        info!("refresh tracker template: {:?}", bbox);
+                                        if let Err(err) = t.init(&mat, bbox) {
+                                            warn!("Can't refresh tracker template: {}", err);
+                                        }
+                                    }
+                                    detections = Some(boxes);
+                                }
+
+                                target = Some(result);
                             } else {
                                 nano_track = None;
                                 last_bbox = None;
+                                lost = true;
+                                publish(&control, Event::Lost { track_id });
                             }
                         } else {
                             nano_track = None;
                             last_bbox = None;
+                            lost = true;
+                            publish(&control, Event::Lost { track_id });
                         }
                     }
 
-                    if nano_track.is_none() {
-                        let mut input = mat_to_ndarray(&mut mat, 640, 640);
-                        let boxes = yolo.infer2(&mut input, w, h);
+                    if nano_track.is_none() && frame_index % throttle.detect_every as u64 == 0 {
+                        let boxes = match detections.take() {
+                            Some(boxes) => boxes,
+                            None => detect(
+                                &mut yolo,
+                                &mut profiler,
+                                &metrics,
+                                &mat,
+                                input_size,
+                                &mut detector_time,
+                            ),
+                        };
                         let mut candidate: Option<Rect> = None;
 
                         if let Some(prev_bbox) = last_bbox {
@@ -206,11 +654,12 @@ fn main() -> opencv::Result<()> {
                             }
                         }
 
-                        println!("boxes len {}", boxes.len());
+                        debug!("boxes len {}", boxes.len());
 
-                        if candidate.is_none() {
+                        // Без оператора — первая детекция; после сброса оператором ждём его выбора
+                        if candidate.is_none() && config.input.auto_acquire && !operator_hold {
                             if let Some(first) = boxes.first() {
-                                println!("first: {:?}", first);
+                                debug!("first: {:?}", first);
                                 candidate = Some(Rect::new(
                                     first.x1 as i32,
                                     first.y1 as i32,
@@ -220,41 +669,72 @@ fn main() -> opencv::Result<()> {
                             }
                         }
 
+                        detections = Some(boxes);
+
                         if let Some(candidate) = candidate {
-                            println!("init tracker: {:?}", candidate);
-                            nano_track = Some(VitTracker::new(candidate, &mat).unwrap());
-                            last_bbox = Some(candidate);
+                            info!("init tracker: {:?}", candidate);
+                            match start_tracker(&throttle.apply(&config.trackers), &mat, candidate)
+                            {
+                                Ok(tracker) => {
+                                    nano_track = Some(tracker);
+                                    track_id += 1;
+                                    template_refresh.reset();
+                                    if acquired_once {
+                                        metrics.reacquisitions.fetch_add(1, Ordering::Relaxed);
+                                    }
+                                    acquired_once = true;
+                                    last_bbox = Some(candidate);
+                                    publish(
+                                        &control,
+                                        Event::Locked {
+                                            track_id,
+                                            bbox: candidate,
+                                            origin: LockOrigin::Detector,
+                                        },
+                                    );
+                                }
+                                // Кандидат отбрасывается, поиск повторится на следующих кадрах
+                                Err(err) => warn!("Can't init tracker on {:?}: {}", candidate, err),
+                            }
                         }
+                    }
 
-                        // let center = center_crop(&mat, 300).unwrap();
-                        // let rows = center.rows();
-                        // let cols = center.cols();
-                        //
-                        // let x = (cols - 50) / 2;
-                        // let y = (rows - 50) / 2;
-                        //
-                        // let roi = core::Rect::new(x, y, 50, 50);
-                        // nano_track = Some(NanoTrack::new(roi, &center).unwrap());
+                    metrics
+                        .tracking
+                        .store(nano_track.is_some(), Ordering::Relaxed);
+                    if let Some(mavlink) = mavlink.as_mut() {
+                        mavlink.update(target.map(|t| t.bbox), w, h);
+                    }
+                    if let Some(gimbal) = gimbal.as_mut() {
+                        gimbal.update(target.map(|t| t.bbox), w, h);
+                    }
+                    if let (Some(control), Some(result)) = (&control, target) {
+                        if control.has_frame_subscribers() {
+                            control.publish(&Event::Track { track_id, result });
+                        }
                     }
 
-                    let cpu = get_cpu_usage();
-                    let mem = get_mem_usage();
-                    let temp = get_cpu_temp();
-
-                    let text = format!("CPU: {:.1}% | RAM: {:.1}% | Temp: {:.1}C", cpu, mem, temp);
-
-                    let _ = imgproc::put_text(
-                        &mut mat,
-                        text.as_str(),
-                        core::Point::new(30, 50),
-                        imgproc::FONT_HERSHEY_SIMPLEX,
-                        1.0,
-                        core::Scalar::new(0.0, 0.0, 255.0, 0.0),
-                        2,
-                        imgproc::LINE_AA,
-                        false,
-                    );
+                    let stage_start = Instant::now();
+                    let state = state_text(nano_track.is_some(), throttle.level);
+                    let frame_overlay = OverlayFrame {
+                        target: target.as_ref(),
+                        track_id: nano_track.as_ref().map(|_| track_id),
+                        detections: detections.as_deref().unwrap_or_default(),
+                        cursor,
+                        selection: dragging,
+                        state: &state,
+                        latency: frame_start.elapsed(),
+                        system: &system,
+                    };
+                    if let Err(err) = overlay.draw(&mut mat, &frame_overlay) {
+                        warn!("Can't draw overlay: {}", err);
+                    }
+                    if let Some(input) = &input {
+                        input.show(&mat);
+                    }
+                    profiler.record(Stage::Overlay, stage_start);
 
+                    let stage_start = Instant::now();
                     let mut out_buffer = gstreamer::Buffer::with_size((w * h * 3) as usize)
                         .expect("Can't get buffer");
                     {
@@ -263,10 +743,56 @@ fn main() -> opencv::Result<()> {
                         out_map.copy_from_slice(mat.data_bytes().unwrap());
                     }
 
-                    match appsrc_thread.push_buffer(out_buffer) {
-                        Ok(_) => {}
+                    let _span = logging::span("push");
+                    let pushed = appsrc_thread.push_buffer(out_buffer);
+                    profiler.record(Stage::Push, stage_start);
+
+                    if telemetry.is_some() || recorder.is_some() {
+                        let state = match (&nano_track, lost, operator_hold) {
+                            (Some(_), _, _) => TrackState::Tracking,
+                            (None, true, _) => TrackState::Lost,
+                            (None, false, true) => TrackState::Hold,
+                            (None, false, false) => TrackState::Searching,
+                        };
+                        let record = Record {
+                            frame_index,
+                            pts_ns: pts.map(|pts| pts.nseconds()),
+                            timestamp_us: SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .map_or(0, |t| t.as_micros() as u64),
+                            state,
+                            target: target.map(|t| telemetry::Target {
+                                track_id,
+                                bbox: t.bbox,
+                                score: t.score,
+                                source: t.source,
+                            }),
+                            detections: detections.iter().flatten().map(Into::into).collect(),
+                            latency: Latency {
+                                frame: frame_start.elapsed(),
+                                detector: detector_time,
+                                tracker: tracker_time,
+                            },
+                        };
+                        if let Some(telemetry) = telemetry.as_mut() {
+                            telemetry.publish(&record);
+                        }
+                        if let Some(recorder) = recorder.as_mut() {
+                            recorder.write(throttle.level, &applied, &record);
+                        }
+                    }
+                    if let Some(boxes) = detections {
+                        last_detections = boxes;
+                    }
+                    last_target = target;
+                    profiler.frame_done();
+                    match pushed {
+                        Ok(_) => {
+                            metrics.frames_processed.fetch_add(1, Ordering::Relaxed);
+                        }
                         Err(err) => {
-                            eprintln!("Can't push buffer: {}", err);
+                            error!("Can't push buffer: {}", err);
+                            metrics.frames_dropped.fetch_add(1, Ordering::Relaxed);
                             continue;
                         }
                     }
@@ -275,8 +801,77 @@ fn main() -> opencv::Result<()> {
         }
     });
 
-    let main_loop = gstreamer::glib::MainLoop::new(None, false);
     main_loop.run();
 
     Ok(())
 }
+
+/// Создаёт трекер из конфига и сразу инициализирует его на рамке.
+fn start_tracker(
+    config: &TrackersConfig,
+    mat: &Mat,
+    bbox: Rect,
+) -> opencv::Result<Box<dyn Tracker>> {
+    let mut tracker = create_tracker(config)?;
+    tracker.init(mat, bbox)?;
+    Ok(tracker)
+}
+
+/// Рассылка события подписчикам управляющего сокета, если он включён.
+fn publish(control: &Option<ControlServer>, event: Event) {
+    if let Some(control) = control {
+        control.publish(&event);
+    }
+}
+
+/// Модель со статическим входом принимает только свой размер: остальные
+/// размеры из конфига игнорируются с предупреждением.
+fn fit_static_input(config: &mut AppConfig, size: i32) {
+    if config.detector.input_size != size {
+        warn!(
+            "Detector model has static input {}, ignore input_size = {}",
+            size, config.detector.input_size
+        );
+        config.detector.input_size = size;
+    }
+    for level in &mut config.thermal.levels {
+        if let Some(input) = level.detector_input.filter(|&input| input != size) {
+            warn!(
+                "Detector model has static input {}, ignore detector_input = {} at {} °C",
+                size, input, level.temp_c
+            );
+            level.detector_input = None;
+        }
+    }
+}
+
+/// Прогон детектора по кадру с замерами стадий.
+fn detect(
+    yolo: &mut YoloV8,
+    profiler: &mut Profiler,
+    metrics: &Metrics,
+    mat: &Mat,
+    input_size: i32,
+    elapsed: &mut Duration,
+) -> Vec<BBox> {
+    let _span = logging::span("detection");
+    let detect_start = Instant::now();
+    let (input, transform) = yolo.prepare(mat, input_size);
+    profiler.record(Stage::Preprocess, detect_start);
+    let stage_start = Instant::now();
+    let output = yolo.inference(&input);
+    profiler.record(Stage::Inference, stage_start);
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            warn!("Detector inference failed: {}", err);
+            return Vec::new();
+        }
+    };
+    let stage_start = Instant::now();
+    let boxes = yolo.decode(&output, &transform);
+    profiler.record(Stage::Postprocess, stage_start);
+    metrics.detector_latency.observe(detect_start.elapsed());
+    *elapsed += detect_start.elapsed();
+    boxes
+}
@@ -0,0 +1,590 @@
+//! Минимальный MAVLink v2: кадры HEARTBEAT, LANDING_TARGET и GIMBAL_MANAGER_SET_PITCHYAW
+//! для автопилота и менеджера подвеса, плюс потоковый декодер для проверки без железа.
+
+use crate::camera::Camera;
+use crate::config::{MavlinkConfig, MavlinkLink};
+use crate::serial;
+use log::{debug, info, warn};
+use opencv::core::Rect;
+use std::fs::File;
+use std::io::{self, Write as _};
+use std::net::UdpSocket;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const STX_V2: u8 = 0xFD;
+const HEADER_LEN: usize = 10;
+const CHECKSUM_LEN: usize = 2;
+
+pub const MSG_HEARTBEAT: u32 = 0;
+pub const MSG_LANDING_TARGET: u32 = 149;
+pub const MSG_GIMBAL_MANAGER_SET_PITCHYAW: u32 = 287;
+
+const MAV_TYPE_CAMERA: u8 = 30;
+const MAV_AUTOPILOT_INVALID: u8 = 8;
+const MAV_STATE_ACTIVE: u8 = 4;
+const MAV_FRAME_BODY_FRD: u8 = 12;
+const LANDING_TARGET_TYPE_VISION_OTHER: u8 = 3;
+
+/// CRC_EXTRA и полная длина полезной нагрузки (с расширениями) для известных сообщений.
+fn message_info(msg_id: u32) -> Option<(u8, usize)> {
+    match msg_id {
+        MSG_HEARTBEAT => Some((50, 9)),
+        MSG_LANDING_TARGET => Some((200, 60)),
+        MSG_GIMBAL_MANAGER_SET_PITCHYAW => Some((1, 23)),
+        _ => None,
+    }
+}
+
+/// CRC-16/MCRF4XX (X.25), как в эталонной реализации MAVLink.
+pub fn crc_accumulate(crc: u16, byte: u8) -> u16 {
+    let mut tmp = byte ^ (crc & 0xff) as u8;
+    tmp ^= tmp << 4;
+    let tmp = tmp as u16;
+    (crc >> 8) ^ (tmp << 8) ^ (tmp << 3) ^ (tmp >> 4)
+}
+
+fn crc(bytes: &[u8], extra: u8) -> u16 {
+    let crc = bytes.iter().fold(0xffff, |crc, &b| crc_accumulate(crc, b));
+    crc_accumulate(crc, extra)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Heartbeat {
+    pub custom_mode: u32,
+    pub mav_type: u8,
+    pub autopilot: u8,
+    pub base_mode: u8,
+    pub system_status: u8,
+    pub mavlink_version: u8,
+}
+
+/// Углы в радианах: `angle_x` вправо, `angle_y` вниз от центра кадра.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LandingTarget {
+    pub time_usec: u64,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    /// Метры, 0 — неизвестно
+    pub distance: f32,
+    pub size_x: f32,
+    pub size_y: f32,
+    pub target_num: u8,
+    pub frame: u8,
+    pub target_type: u8,
+}
+
+/// Углы и скорости в радианах; NaN — поле игнорируется подвесом.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GimbalManagerSetPitchyaw {
+    pub flags: u32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub pitch_rate: f32,
+    pub yaw_rate: f32,
+    pub target_system: u8,
+    pub target_component: u8,
+    pub gimbal_device_id: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Heartbeat(Heartbeat),
+    LandingTarget(LandingTarget),
+    GimbalManagerSetPitchyaw(GimbalManagerSetPitchyaw),
+}
+
+impl Message {
+    pub fn id(&self) -> u32 {
+        match self {
+            Message::Heartbeat(_) => MSG_HEARTBEAT,
+            Message::LandingTarget(_) => MSG_LANDING_TARGET,
+            Message::GimbalManagerSetPitchyaw(_) => MSG_GIMBAL_MANAGER_SET_PITCHYAW,
+        }
+    }
+
+    /// Поля в порядке MAVLink: по убыванию размера типа, затем расширения.
+    fn payload(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64);
+        match self {
+            Message::Heartbeat(m) => {
+                out.extend_from_slice(&m.custom_mode.to_le_bytes());
+                out.extend_from_slice(&[
+                    m.mav_type,
+                    m.autopilot,
+                    m.base_mode,
+                    m.system_status,
+                    m.mavlink_version,
+                ]);
+            }
+            Message::LandingTarget(m) => {
+                out.extend_from_slice(&m.time_usec.to_le_bytes());
+                for v in [m.angle_x, m.angle_y, m.distance, m.size_x, m.size_y] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                out.extend_from_slice(&[m.target_num, m.frame]);
+                // Расширения: x, y, z, q[4] не заполняются, position_valid = 0
+                for _ in 0..7 {
+                    out.extend_from_slice(&0f32.to_le_bytes());
+                }
+                out.extend_from_slice(&[m.target_type, 0]);
+            }
+            Message::GimbalManagerSetPitchyaw(m) => {
+                out.extend_from_slice(&m.flags.to_le_bytes());
+                for v in [m.pitch, m.yaw, m.pitch_rate, m.yaw_rate] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                out.extend_from_slice(&[m.target_system, m.target_component, m.gimbal_device_id]);
+            }
+        }
+        out
+    }
+
+    fn decode(msg_id: u32, payload: &[u8]) -> Option<Message> {
+        let u32_at = |i: usize| u32::from_le_bytes(payload[i..i + 4].try_into().unwrap());
+        let f32_at = |i: usize| f32::from_le_bytes(payload[i..i + 4].try_into().unwrap());
+        Some(match msg_id {
+            MSG_HEARTBEAT => Message::Heartbeat(Heartbeat {
+                custom_mode: u32_at(0),
+                mav_type: payload[4],
+                autopilot: payload[5],
+                base_mode: payload[6],
+                system_status: payload[7],
+                mavlink_version: payload[8],
+            }),
+            MSG_LANDING_TARGET => Message::LandingTarget(LandingTarget {
+                time_usec: u64::from_le_bytes(payload[0..8].try_into().unwrap()),
+                angle_x: f32_at(8),
+                angle_y: f32_at(12),
+                distance: f32_at(16),
+                size_x: f32_at(20),
+                size_y: f32_at(24),
+                target_num: payload[28],
+                frame: payload[29],
+                target_type: payload[58],
+            }),
+            MSG_GIMBAL_MANAGER_SET_PITCHYAW => {
+                Message::GimbalManagerSetPitchyaw(GimbalManagerSetPitchyaw {
+                    flags: u32_at(0),
+                    pitch: f32_at(4),
+                    yaw: f32_at(8),
+                    pitch_rate: f32_at(12),
+                    yaw_rate: f32_at(16),
+                    target_system: payload[20],
+                    target_component: payload[21],
+                    gimbal_device_id: payload[22],
+                })
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// Кадр MAVLink v2 после проверки контрольной суммы.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub seq: u8,
+    pub system_id: u8,
+    pub component_id: u8,
+    pub message: Message,
+}
+
+pub fn encode(message: &Message, seq: u8, system_id: u8, component_id: u8) -> Vec<u8> {
+    let msg_id = message.id();
+    let (crc_extra, _) = message_info(msg_id).unwrap();
+    let mut payload = message.payload();
+    // В v2 нулевые байты в конце полезной нагрузки отбрасываются, но не первый
+    while payload.len() > 1 && payload.last() == Some(&0) {
+        payload.pop();
+    }
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len() + CHECKSUM_LEN);
+    frame.extend_from_slice(&[
+        STX_V2,
+        payload.len() as u8,
+        0,
+        0,
+        seq,
+        system_id,
+        component_id,
+    ]);
+    frame.extend_from_slice(&msg_id.to_le_bytes()[..3]);
+    frame.extend_from_slice(&payload);
+    let checksum = crc(&frame[1..], crc_extra);
+    frame.extend_from_slice(&checksum.to_le_bytes());
+    frame
+}
+
+/// Потоковый декодер: байты приходят кусками, кадры собираются по мере готовности.
+/// Мусор, кадры с неверной суммой и сообщения вне поддерживаемого набора пропускаются:
+/// без CRC_EXTRA их сумму не проверить.
+#[derive(Default)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+    pub crc_errors: u64,
+}
+
+impl Decoder {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Frame> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+
+        loop {
+            match self.buffer.iter().position(|&b| b == STX_V2) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    self.buffer.clear();
+                    break;
+                }
+            }
+            if self.buffer.len() < HEADER_LEN {
+                break;
+            }
+            let payload_len = self.buffer[1] as usize;
+            let msg_id = u32::from_le_bytes([self.buffer[7], self.buffer[8], self.buffer[9], 0]);
+            let Some((crc_extra, full_len)) = message_info(msg_id) else {
+                // Неизвестное сообщение или ложный STX — ищем следующий
+                self.buffer.drain(..1);
+                continue;
+            };
+            // Подписанные кадры (incompat_flags & 1) несут ещё 13 байт подписи
+            let signature_len = if self.buffer[2] & 0x01 != 0 { 13 } else { 0 };
+            let total = HEADER_LEN + payload_len + CHECKSUM_LEN + signature_len;
+            if self.buffer.len() < total {
+                break;
+            }
+
+            let body_end = HEADER_LEN + payload_len;
+            let received = u16::from_le_bytes([self.buffer[body_end], self.buffer[body_end + 1]]);
+            if crc(&self.buffer[1..body_end], crc_extra) != received {
+                self.crc_errors += 1;
+                self.buffer.drain(..1);
+                continue;
+            }
+
+            let mut payload = self.buffer[HEADER_LEN..body_end].to_vec();
+            payload.resize(full_len.max(payload_len), 0);
+            if let Some(message) = Message::decode(msg_id, &payload) {
+                frames.push(Frame {
+                    seq: self.buffer[4],
+                    system_id: self.buffer[5],
+                    component_id: self.buffer[6],
+                    message,
+                });
+            }
+            self.buffer.drain(..total);
+        }
+        frames
+    }
+}
+
+enum Link {
+    Udp(UdpSocket),
+    Serial(File),
+    /// Кадры уходят в канал; для проверки без автопилота
+    Loopback(Sender<Vec<u8>>),
+}
+
+impl Link {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        match self {
+            Link::Udp(socket) => socket.send(frame).map(|_| ()),
+            Link::Serial(port) => port.write_all(frame),
+            Link::Loopback(tx) => tx
+                .send(frame.to_vec())
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Loopback closed")),
+        }
+    }
+}
+
+fn unix_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_micros() as u64)
+}
+
+/// Отправляет положение цели автопилоту и команды скорости менеджеру подвеса.
+pub struct MavlinkEmitter {
+    config: MavlinkConfig,
+    camera: Camera,
+    link: Link,
+    seq: u8,
+    last_heartbeat: Option<Instant>,
+    had_target: bool,
+}
+
+impl MavlinkEmitter {
+    pub fn new(config: &MavlinkConfig, camera: Camera) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let link = match config.link {
+            MavlinkLink::Udp => UdpSocket::bind("0.0.0.0:0")
+                .and_then(|socket| socket.connect(&config.udp).map(|_| socket))
+                .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+                .map(Link::Udp),
+            MavlinkLink::Serial => serial::open(&config.serial, config.baud).map(Link::Serial),
+            MavlinkLink::Loopback => {
+                let (emitter, rx) = Self::loopback(config, camera);
+                // Собственные кадры разбираются обратно и пишутся в лог
+                std::thread::spawn(move || {
+                    let mut decoder = Decoder::default();
+                    for bytes in rx {
+                        for frame in decoder.push(&bytes) {
+                            debug!("mavlink loopback: {:?}", frame);
+                        }
+                    }
+                });
+                return Some(emitter);
+            }
+        };
+
+        match link {
+            Ok(link) => {
+                info!("MAVLink {:?} output", config.link);
+                Some(Self::with_link(config, camera, link))
+            }
+            Err(err) => {
+                warn!("Can't open MAVLink {:?} link: {}", config.link, err);
+                None
+            }
+        }
+    }
+
+    /// Эмиттер, отдающий закодированные кадры в канал.
+    pub fn loopback(config: &MavlinkConfig, camera: Camera) -> (Self, Receiver<Vec<u8>>) {
+        let (tx, rx) = mpsc::channel();
+        (Self::with_link(config, camera, Link::Loopback(tx)), rx)
+    }
+
+    fn with_link(config: &MavlinkConfig, camera: Camera, link: Link) -> Self {
+        Self {
+            config: config.clone(),
+            camera,
+            link,
+            seq: 0,
+            last_heartbeat: None,
+            had_target: false,
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        let frame = encode(
+            &message,
+            self.seq,
+            self.config.system_id,
+            self.config.component_id,
+        );
+        self.seq = self.seq.wrapping_add(1);
+        if let Err(err) = self.link.send(&frame) {
+            debug!("Can't send MAVLink message {}: {}", message.id(), err);
+        }
+    }
+
+    fn gimbal_rates(&mut self, pitch_rate: f32, yaw_rate: f32) {
+        self.send(Message::GimbalManagerSetPitchyaw(
+            GimbalManagerSetPitchyaw {
+                flags: 0,
+                pitch: f32::NAN,
+                yaw: f32::NAN,
+                pitch_rate,
+                yaw_rate,
+                target_system: self.config.target_system,
+                target_component: self.config.target_component,
+                gimbal_device_id: self.config.gimbal_device_id,
+            },
+        ));
+    }
+
+    /// Вызывается на каждом кадре; без цели подвес один раз останавливается.
+    pub fn update(&mut self, target: Option<Rect>, width: i32, height: i32) {
+        let interval = Duration::from_secs_f64(self.config.heartbeat_interval_secs);
+        if self
+            .last_heartbeat
+            .is_none_or(|at| at.elapsed() >= interval)
+        {
+            self.last_heartbeat = Some(Instant::now());
+            self.send(Message::Heartbeat(Heartbeat {
+                custom_mode: 0,
+                mav_type: MAV_TYPE_CAMERA,
+                autopilot: MAV_AUTOPILOT_INVALID,
+                base_mode: 0,
+                system_status: MAV_STATE_ACTIVE,
+                mavlink_version: 3,
+            }));
+        }
+
+        let Some(bbox) = target else {
+            if self.had_target && self.config.gimbal {
+                self.gimbal_rates(0.0, 0.0);
+            }
+            self.had_target = false;
+            return;
+        };
+        self.had_target = true;
+
+        let offset = self.camera.offset(bbox, width, height);
+        let (size_x, size_y) = self.camera.angular_size(bbox, width, height);
+        let yaw = offset.yaw_deg.to_radians();
+        let pitch = offset.pitch_deg.to_radians();
+
+        if self.config.landing_target {
+            self.send(Message::LandingTarget(LandingTarget {
+                time_usec: unix_micros(),
+                angle_x: yaw,
+                // LANDING_TARGET считает y вниз, как в изображении
+                angle_y: -pitch,
+                distance: 0.0,
+                size_x: size_x.to_radians(),
+                size_y: size_y.to_radians(),
+                target_num: 0,
+                frame: MAV_FRAME_BODY_FRD,
+                target_type: LANDING_TARGET_TYPE_VISION_OTHER,
+            }));
+        }
+
+        if self.config.gimbal {
+            let max_rate = self.config.max_rate_deg.to_radians();
+            let gain = self.config.rate_gain;
+            self.gimbal_rates(
+                (pitch * gain).clamp(-max_rate, max_rate),
+                (yaw * gain).clamp(-max_rate, max_rate),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat() -> Message {
+        Message::Heartbeat(Heartbeat {
+            custom_mode: 0,
+            mav_type: MAV_TYPE_CAMERA,
+            autopilot: MAV_AUTOPILOT_INVALID,
+            base_mode: 0,
+            system_status: MAV_STATE_ACTIVE,
+            mavlink_version: 3,
+        })
+    }
+
+    fn landing_target() -> Message {
+        Message::LandingTarget(LandingTarget {
+            time_usec: 1_700_000_000_123_456,
+            angle_x: 0.12,
+            angle_y: -0.05,
+            distance: 0.0,
+            size_x: 0.02,
+            size_y: 0.03,
+            target_num: 1,
+            frame: MAV_FRAME_BODY_FRD,
+            target_type: LANDING_TARGET_TYPE_VISION_OTHER,
+        })
+    }
+
+    fn set_pitchyaw(target_system: u8) -> Message {
+        Message::GimbalManagerSetPitchyaw(GimbalManagerSetPitchyaw {
+            flags: 0,
+            pitch: 0.0,
+            yaw: 0.0,
+            pitch_rate: -0.25,
+            yaw_rate: 0.5,
+            target_system,
+            target_component: 0,
+            gimbal_device_id: 0,
+        })
+    }
+
+    #[test]
+    fn crc_check_value() {
+        // Контрольное значение CRC-16/MCRF4XX для "123456789"
+        let crc = b"123456789"
+            .iter()
+            .fold(0xffff, |crc, &b| crc_accumulate(crc, b));
+        assert_eq!(crc, 0x6F91);
+    }
+
+    #[test]
+    fn heartbeat_frame() {
+        let frame = encode(&heartbeat(), 7, 1, 100);
+        assert_eq!(
+            frame,
+            [
+                0xFD, 0x09, 0x00, 0x00, 0x07, 0x01, 0x64, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x1E, 0x08, 0x00, 0x04, 0x03, 0x22, 0x3A
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let messages = [heartbeat(), landing_target(), set_pitchyaw(1)];
+        let mut decoder = Decoder::default();
+        let mut frames = Vec::new();
+        for (seq, message) in messages.iter().enumerate() {
+            // По байту, как из последовательного порта
+            for byte in encode(message, seq as u8, 1, 154) {
+                frames.extend(decoder.push(&[byte]));
+            }
+        }
+        assert_eq!(frames.len(), messages.len());
+        for (seq, (frame, message)) in frames.iter().zip(&messages).enumerate() {
+            assert_eq!(
+                (frame.seq, frame.system_id, frame.component_id),
+                (seq as u8, 1, 154)
+            );
+            assert_eq!(&frame.message, message);
+        }
+        assert_eq!(decoder.crc_errors, 0);
+    }
+
+    #[test]
+    fn trailing_zeros_are_truncated() {
+        // Нулевые цель и подвес: полезная нагрузка заканчивается на yaw_rate
+        let message = set_pitchyaw(0);
+        let frame = encode(&message, 0, 1, 154);
+        assert_eq!(frame[1], 20);
+        assert_eq!(frame.len(), HEADER_LEN + 20 + CHECKSUM_LEN);
+
+        // Первый байт полезной нагрузки остаётся даже нулевым
+        let empty = Message::Heartbeat(Heartbeat {
+            custom_mode: 0,
+            mav_type: 0,
+            autopilot: 0,
+            base_mode: 0,
+            system_status: 0,
+            mavlink_version: 0,
+        });
+        assert_eq!(encode(&empty, 0, 1, 154)[1], 1);
+
+        let mut decoder = Decoder::default();
+        let frames =
+            decoder.push(&[encode(&message, 0, 1, 154), encode(&empty, 1, 1, 154)].concat());
+        let messages: Vec<_> = frames.into_iter().map(|frame| frame.message).collect();
+        assert_eq!(messages, [message, empty]);
+    }
+
+    #[test]
+    fn resync_after_garbage_and_bad_crc() {
+        let mut corrupted = encode(&landing_target(), 0, 1, 154);
+        corrupted[HEADER_LEN + 3] ^= 0x40;
+        let mut bytes = vec![0x00, STX_V2, 0x13, 0xFF, STX_V2];
+        bytes.extend(&corrupted);
+        bytes.extend([0xAA, 0x55]);
+        bytes.extend(encode(&heartbeat(), 1, 1, 154));
+
+        let mut decoder = Decoder::default();
+        let frames: Vec<Frame> = bytes
+            .chunks(7)
+            .flat_map(|chunk| decoder.push(chunk))
+            .collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].seq, 1);
+        assert_eq!(frames[0].message, heartbeat());
+        assert_eq!(decoder.crc_errors, 1);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::sysmon::SystemStats;
+use log::{info, warn};
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Границы бакетов гистограмм задержек, секунды.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.02, 0.03, 0.05, 0.075, 0.1, 0.15, 0.2, 0.3, 0.5, 1.0,
+];
+
+/// Значение метки в формате Prometheus: экранируются только `\`, `"` и перевод строки.
+fn escape_label(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_us: AtomicU64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: buckets.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, value: Duration) {
+        let secs = value.as_secs_f64();
+        // Бакеты хранятся некумулятивно, суммируются при выводе
+        if let Some(i) = self.buckets.iter().position(|&le| secs <= le) {
+            self.counts[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us
+            .fetch_add(value.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn write(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (le, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, cumulative);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let sum = self.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+/// Счётчики пайплайна, общие для потока обработки и HTTP-сервера.
+pub struct Metrics {
+    pub frames_processed: AtomicU64,
+    pub frames_dropped: AtomicU64,
+    pub reacquisitions: AtomicU64,
+    pub tracking: AtomicBool,
+    pub thermal_level: AtomicU64,
+    pub detector_latency: Histogram,
+    pub tracker_latency: Histogram,
+    pub system: Arc<SystemStats>,
+}
+
+impl Metrics {
+    pub fn new(system: Arc<SystemStats>) -> Self {
+        Self {
+            frames_processed: AtomicU64::new(0),
+            frames_dropped: AtomicU64::new(0),
+            reacquisitions: AtomicU64::new(0),
+            tracking: AtomicBool::new(false),
+            thermal_level: AtomicU64::new(0),
+            detector_latency: Histogram::new(&LATENCY_BUCKETS),
+            tracker_latency: Histogram::new(&LATENCY_BUCKETS),
+            system,
+        }
+    }
+
+    /// Текстовый формат экспозиции Prometheus.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        counter(
+            &mut out,
+            "nano_frames_processed_total",
+            "Frames pushed to the output pipeline",
+            &self.frames_processed,
+        );
+        counter(
+            &mut out,
+            "nano_frames_dropped_total",
+            "Frames lost on pull, map or push",
+            &self.frames_dropped,
+        );
+        counter(
+            &mut out,
+            "nano_reacquisitions_total",
+            "Tracker initialisations after the target was lost",
+            &self.reacquisitions,
+        );
+        gauge(
+            &mut out,
+            "nano_tracking",
+            "1 while a target is tracked",
+            self.tracking.load(Ordering::Relaxed) as u8 as f64,
+        );
+
+        self.detector_latency.write(
+            &mut out,
+            "nano_detector_latency_seconds",
+            "Detector preprocess + inference + postprocess",
+        );
+        self.tracker_latency
+            .write(&mut out, "nano_tracker_latency_seconds", "Tracker update");
+
+        let system = &self.system;
+        gauge(
+            &mut out,
+            "nano_cpu_usage_percent",
+            "CPU usage over the last sampling interval",
+            system.cpu.load() as f64,
+        );
+        let _ = writeln!(out, "# HELP nano_cpu_core_usage_percent Per-core CPU usage");
+        let _ = writeln!(out, "# TYPE nano_cpu_core_usage_percent gauge");
+        for (core, usage) in system.per_core.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "nano_cpu_core_usage_percent{{core=\"{}\"}} {}",
+                core,
+                usage.load()
+            );
+        }
+        gauge(
+            &mut out,
+            "nano_process_cpu_percent",
+            "CPU used by this process, share of all cores",
+            system.process_cpu.load() as f64,
+        );
+        gauge(
+            &mut out,
+            "nano_process_rss_bytes",
+            "Resident set size of this process",
+            system.process_rss_bytes.load(Ordering::Relaxed) as f64,
+        );
+        gauge(
+            &mut out,
+            "nano_mem_usage_percent",
+            "Memory usage",
+            system.mem.load() as f64,
+        );
+        gauge(
+            &mut out,
+            "nano_cpu_temp_celsius",
+            "Temperature of the hottest thermal zone",
+            system.temp.load() as f64,
+        );
+        gauge(
+            &mut out,
+            "nano_thermal_level",
+            "Active thermal throttling level, 0 when not throttled",
+            self.thermal_level.load(Ordering::Relaxed) as f64,
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP nano_thermal_zone_celsius Temperature of each thermal zone"
+        );
+        let _ = writeln!(out, "# TYPE nano_thermal_zone_celsius gauge");
+        for zone in &system.zones {
+            let _ = writeln!(
+                out,
+                "nano_thermal_zone_celsius{{zone=\"{}\",type=\"{}\"}} {}",
+                zone.index,
+                escape_label(&zone.kind),
+                zone.temp_c.load()
+            );
+        }
+        let _ = writeln!(out, "# HELP nano_cpu_freq_hz Current CPU frequency");
+        let _ = writeln!(out, "# TYPE nano_cpu_freq_hz gauge");
+        for freq in &system.freqs {
+            let _ = writeln!(
+                out,
+                "nano_cpu_freq_hz{{cpu=\"{}\",governor=\"{}\"}} {}",
+                freq.cpu,
+                escape_label(&freq.governor),
+                freq.cur_khz.load(Ordering::Relaxed) * 1000
+            );
+        }
+        let _ = writeln!(
+            out,
+            "# HELP nano_cpu_max_freq_hz Maximum allowed CPU frequency"
+        );
+        let _ = writeln!(out, "# TYPE nano_cpu_max_freq_hz gauge");
+        for freq in &system.freqs {
+            let _ = writeln!(
+                out,
+                "nano_cpu_max_freq_hz{{cpu=\"{}\"}} {}",
+                freq.cpu,
+                freq.max_khz.load(Ordering::Relaxed) * 1000
+            );
+        }
+
+        out
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: &AtomicU64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Поднимает HTTP-сервер с `GET /metrics` в отдельном потоке.
+pub fn serve(listen: &str, metrics: Arc<Metrics>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(listen)?;
+    info!("Metrics on http://{}/metrics", listen);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = handle(stream, &metrics) {
+                        warn!("Metrics request failed: {}", err);
+                    }
+                }
+                Err(err) => warn!("Metrics connection failed: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    let mut request_line = String::new();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    reader.read_line(&mut request_line)?;
+    // Заголовки не нужны, но их надо дочитать до пустой строки
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, content_type, body) = if request_line.starts_with("GET ") && path == "/metrics" {
+        ("200 OK", "text/plain; version=0.0.4", metrics.render())
+    } else {
+        ("404 Not Found", "text/plain", "Not found\n".to_string())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_escaping() {
+        assert_eq!(escape_label("cpu-thermal"), "cpu-thermal");
+        assert_eq!(escape_label("a\\b \"c\"\nd\té"), "a\\\\b \\\"c\\\"\\nd\té");
+    }
+}
@@ -0,0 +1,270 @@
+use crate::camera::Camera;
+use crate::config::{Color, OverlayConfig};
+use crate::hud::Hud;
+use crate::sysmon::SystemStats;
+use crate::trackers::TrackResult;
+use crate::utils::{BBox, draw_bboxes, expand_roi_rect};
+use crate::yolo::COCO_CLASSES;
+use opencv::core::{Point, Rect, Scalar, Vector};
+use opencv::imgproc;
+use opencv::prelude::*;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Что известно о кадре к моменту отрисовки.
+pub struct OverlayFrame<'a> {
+    pub target: Option<&'a TrackResult>,
+    /// Номер захвата цели; меняется при каждой инициализации трекера
+    pub track_id: Option<u64>,
+    pub detections: &'a [BBox],
+    /// Курсор оператора и рамка, которую он тянет
+    pub cursor: Option<Point>,
+    pub selection: Option<Rect>,
+    pub state: &'a str,
+    /// Время от получения кадра до отрисовки
+    pub latency: Duration,
+    pub system: &'a SystemStats,
+}
+
+/// Отрисовка слоёв оверлея. Толщина, шрифт и позиции заданы для кадра
+/// высотой `reference_height` и масштабируются под фактическое разрешение.
+pub struct OverlayRenderer {
+    config: OverlayConfig,
+    trail: VecDeque<Point>,
+    trail_id: Option<u64>,
+    last_frame: Option<Instant>,
+    fps: f64,
+    hud: Option<Hud>,
+}
+
+pub(crate) fn scalar(color: Color) -> Scalar {
+    Scalar::new(color[0], color[1], color[2], 0.0)
+}
+
+/// Оформление, пересчитанное под высоту текущего кадра.
+pub(crate) struct Style {
+    pub scale: f64,
+    pub thickness: i32,
+    pub font_scale: f64,
+}
+
+impl Style {
+    fn new(config: &OverlayConfig, frame_height: i32) -> Self {
+        let scale = frame_height as f64 / config.reference_height.max(1) as f64;
+        Self {
+            scale,
+            thickness: ((config.thickness as f64 * scale).round() as i32).max(1),
+            font_scale: config.font_scale * scale,
+        }
+    }
+
+    /// Длина в пикселях опорного кадра -> в пикселях текущего.
+    pub fn px(&self, value: i32) -> i32 {
+        (value as f64 * self.scale).round() as i32
+    }
+
+    pub fn point(&self, p: [i32; 2]) -> Point {
+        Point::new(self.px(p[0]), self.px(p[1]))
+    }
+}
+
+fn center(rect: Rect) -> Point {
+    Point::new(rect.x + rect.width / 2, rect.y + rect.height / 2)
+}
+
+impl OverlayRenderer {
+    pub fn new(config: &OverlayConfig, camera: Camera) -> Self {
+        Self {
+            config: config.clone(),
+            trail: VecDeque::with_capacity(config.trail_length),
+            trail_id: None,
+            last_frame: None,
+            fps: 0.0,
+            hud: config.hud.enabled.then(|| Hud::new(&config.hud, camera)),
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Mat, data: &OverlayFrame) -> opencv::Result<()> {
+        self.update_fps();
+        self.update_trail(data);
+        if let Some(hud) = self.hud.as_mut() {
+            hud.update(data.target, data.track_id);
+        }
+
+        let config = &self.config;
+        let style = Style::new(config, frame.rows());
+        let (scale, thickness, font_scale) = (style.scale, style.thickness, style.font_scale);
+
+        if config.detections {
+            draw_bboxes(
+                frame,
+                data.detections,
+                &COCO_CLASSES,
+                scalar(config.colors.detections),
+                font_scale * 0.5,
+                (thickness / 2).max(1),
+            )?;
+        }
+
+        if config.trail && self.trail.len() > 1 {
+            let points: Vector<Point> = self.trail.iter().copied().collect();
+            let mut polylines = Vector::<Vector<Point>>::new();
+            polylines.push(points);
+            imgproc::polylines(
+                frame,
+                &polylines,
+                false,
+                scalar(config.colors.trail),
+                thickness,
+                imgproc::LINE_AA,
+                0,
+            )?;
+        }
+
+        if let Some(target) = data.target {
+            if config.search_roi {
+                let roi = expand_roi_rect(&*frame, target.bbox, config.search_roi_margin)?;
+                imgproc::rectangle(
+                    frame,
+                    roi,
+                    scalar(config.colors.search_roi),
+                    (thickness / 2).max(1),
+                    imgproc::LINE_8,
+                    0,
+                )?;
+            }
+
+            if config.target {
+                let bbox = target.bbox;
+                imgproc::rectangle(
+                    frame,
+                    bbox,
+                    scalar(config.colors.target),
+                    thickness,
+                    imgproc::LINE_8,
+                    0,
+                )?;
+
+                let mut label = format!("{} {:.2}", target.source.name(), target.score);
+                if let (true, Some(id)) = (config.track_id, data.track_id) {
+                    label = format!("#{} {}", id, label);
+                }
+                imgproc::put_text(
+                    frame,
+                    &label,
+                    Point::new(
+                        bbox.x,
+                        (bbox.y - (8.0 * scale) as i32).max((20.0 * scale) as i32),
+                    ),
+                    imgproc::FONT_HERSHEY_SIMPLEX,
+                    font_scale * 0.7,
+                    scalar(config.colors.target),
+                    thickness,
+                    imgproc::LINE_AA,
+                    false,
+                )?;
+            }
+        }
+
+        let selection = scalar(config.colors.selection);
+        if let Some(rect) = data.selection {
+            imgproc::rectangle(frame, rect, selection, thickness, imgproc::LINE_8, 0)?;
+        }
+        if let Some(at) = data.cursor {
+            imgproc::draw_marker(
+                frame,
+                at,
+                selection,
+                imgproc::MARKER_CROSS,
+                style.px(30),
+                thickness,
+                imgproc::LINE_8,
+            )?;
+        }
+
+        if let Some(hud) = &self.hud {
+            hud.draw(frame, data.target, &style)?;
+        }
+
+        let mut lines = Vec::new();
+        if config.system {
+            let system = data.system;
+            lines.push((
+                config.system_position,
+                format!(
+                    "CPU: {:.1}% | RAM: {:.1}% | Temp: {:.1}C",
+                    system.cpu.load(),
+                    system.mem.load(),
+                    system.temp.load()
+                ),
+            ));
+        }
+        if config.state {
+            lines.push((config.state_position, data.state.to_string()));
+        }
+        if config.fps {
+            lines.push((
+                config.fps_position,
+                format!(
+                    "FPS: {:.1} | Latency: {:.1} ms",
+                    self.fps,
+                    data.latency.as_secs_f64() * 1000.0
+                ),
+            ));
+        }
+        for (origin, text) in lines {
+            imgproc::put_text(
+                frame,
+                &text,
+                style.point(origin),
+                imgproc::FONT_HERSHEY_SIMPLEX,
+                font_scale,
+                scalar(config.colors.text),
+                thickness,
+                imgproc::LINE_AA,
+                false,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn update_fps(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame.replace(now) {
+            let dt = now.duration_since(last).as_secs_f64();
+            if dt > 0.0 {
+                // Экспоненциальное сглаживание, чтобы цифра не прыгала
+                self.fps = if self.fps == 0.0 {
+                    1.0 / dt
+                } else {
+                    self.fps * 0.9 + 0.1 / dt
+                };
+            }
+        }
+    }
+
+    fn update_trail(&mut self, data: &OverlayFrame) {
+        let Some(target) = data.target else {
+            self.trail.clear();
+            return;
+        };
+        if data.track_id != self.trail_id {
+            self.trail.clear();
+            self.trail_id = data.track_id;
+        }
+        if self.trail.len() >= self.config.trail_length.max(1) {
+            self.trail.pop_front();
+        }
+        self.trail.push_back(center(target.bbox));
+    }
+}
+
+/// Строка состояния для слоя `state`.
+pub fn state_text(tracking: bool, thermal_level: usize) -> String {
+    let mut state = if tracking { "TRACKING" } else { "SEARCHING" }.to_string();
+    if thermal_level > 0 {
+        state.push_str(&format!(" | THERMAL L{}", thermal_level));
+    }
+    state
+}
@@ -0,0 +1,300 @@
+//! Pelco-D: 7 байт — 0xFF, адрес, команда 1, команда 2, данные 1, данные 2, сумма.
+
+use crate::gimbal::{Actuator, RateCommand};
+use log::debug;
+use std::fs::File;
+use std::io::{self, Read as _, Write as _};
+
+pub const SYNC: u8 = 0xFF;
+pub const FRAME_LEN: usize = 7;
+
+pub const RIGHT: u8 = 0x02;
+pub const LEFT: u8 = 0x04;
+pub const UP: u8 = 0x08;
+pub const DOWN: u8 = 0x10;
+/// Наибольшая обычная скорость; 0xFF («турбо») не используется
+pub const MAX_SPEED: u8 = 0x3F;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub address: u8,
+    pub command1: u8,
+    pub command2: u8,
+    pub data1: u8,
+    pub data2: u8,
+}
+
+impl Frame {
+    pub fn encode(&self) -> [u8; FRAME_LEN] {
+        let body = [
+            self.address,
+            self.command1,
+            self.command2,
+            self.data1,
+            self.data2,
+        ];
+        let sum = body.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        [SYNC, body[0], body[1], body[2], body[3], body[4], sum]
+    }
+
+    /// Скорость pan (`data1`) и tilt (`data2`) в долях `MAX_SPEED`
+    /// от `max_rate` град/с; знак задаёт направление.
+    pub fn pan_tilt(address: u8, command: RateCommand, max_rate: f32) -> Self {
+        let speed =
+            |rate: f32| ((rate.abs() / max_rate * MAX_SPEED as f32).round() as u8).min(MAX_SPEED);
+        let (pan, tilt) = (speed(command.pan), speed(command.tilt));
+        let mut command2 = 0;
+        if pan > 0 {
+            command2 |= if command.pan > 0.0 { RIGHT } else { LEFT };
+        }
+        if tilt > 0 {
+            command2 |= if command.tilt > 0.0 { UP } else { DOWN };
+        }
+        Self {
+            address,
+            command1: 0,
+            command2,
+            data1: pan,
+            data2: tilt,
+        }
+    }
+
+    /// Обратное к [`Frame::pan_tilt`]: скорости в град/с с точностью до шага.
+    pub fn rates(&self, max_rate: f32) -> RateCommand {
+        let rate = |speed: u8, positive: bool, negative: bool| {
+            let value = speed.min(MAX_SPEED) as f32 / MAX_SPEED as f32 * max_rate;
+            match (positive, negative) {
+                (true, false) => value,
+                (false, true) => -value,
+                _ => 0.0,
+            }
+        };
+        RateCommand {
+            pan: rate(
+                self.data1,
+                self.command2 & RIGHT != 0,
+                self.command2 & LEFT != 0,
+            ),
+            tilt: rate(
+                self.data2,
+                self.command2 & UP != 0,
+                self.command2 & DOWN != 0,
+            ),
+        }
+    }
+}
+
+/// Потоковый декодер; при неверной сумме ищется следующий 0xFF.
+#[derive(Default)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+    pub checksum_errors: u64,
+}
+
+impl Decoder {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Frame> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+
+        loop {
+            match self.buffer.iter().position(|&b| b == SYNC) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    self.buffer.clear();
+                    break;
+                }
+            }
+            if self.buffer.len() < FRAME_LEN {
+                break;
+            }
+            let frame = Frame {
+                address: self.buffer[1],
+                command1: self.buffer[2],
+                command2: self.buffer[3],
+                data1: self.buffer[4],
+                data2: self.buffer[5],
+            };
+            if frame.encode()[6] != self.buffer[6] {
+                self.checksum_errors += 1;
+                self.buffer.drain(..1);
+                continue;
+            }
+            frames.push(frame);
+            self.buffer.drain(..FRAME_LEN);
+        }
+        frames
+    }
+}
+
+/// Сторона устройства на псевдотерминале: принятые кадры пишутся в лог.
+pub fn monitor(mut master: File, max_rate: f32) {
+    let mut decoder = Decoder::default();
+    let mut buf = [0u8; 256];
+    while let Ok(n @ 1..) = master.read(&mut buf) {
+        for frame in decoder.push(&buf[..n]) {
+            debug!(
+                "Pelco-D device {}: {:?}",
+                frame.address,
+                frame.rates(max_rate)
+            );
+        }
+    }
+}
+
+/// Поворотное устройство Pelco-D; углов не сообщает, так что регулятор их оценивает сам.
+pub struct PelcoDActuator {
+    port: File,
+    address: u8,
+    max_rate: f32,
+    /// Повторять одинаковые кадры незачем
+    last: Option<Frame>,
+}
+
+impl PelcoDActuator {
+    pub fn new(port: File, address: u8, max_rate: f32) -> Self {
+        Self {
+            port,
+            address,
+            max_rate,
+            last: None,
+        }
+    }
+}
+
+impl Actuator for PelcoDActuator {
+    fn set_rates(&mut self, command: RateCommand) -> io::Result<()> {
+        let frame = Frame::pan_tilt(self.address, command, self.max_rate);
+        if self.last != Some(frame) {
+            self.port.write_all(&frame.encode())?;
+            self.last = Some(frame);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial;
+
+    #[test]
+    fn pan_tilt_frame() {
+        // 30.476 из 60 град/с — шаг 0x20 вправо
+        let frame = Frame::pan_tilt(
+            1,
+            RateCommand {
+                pan: 30.476,
+                tilt: 0.0,
+            },
+            60.0,
+        );
+        assert_eq!(frame.encode(), [0xFF, 0x01, 0x00, 0x02, 0x20, 0x00, 0x23]);
+        let frame = Frame::pan_tilt(
+            2,
+            RateCommand {
+                pan: -90.0,
+                tilt: 15.0,
+            },
+            60.0,
+        );
+        assert_eq!(
+            frame.encode(),
+            [0xFF, 0x02, 0x00, LEFT | UP, MAX_SPEED, 0x10, 0x5D]
+        );
+    }
+
+    #[test]
+    fn rates_round_trip() {
+        let step = 60.0 / MAX_SPEED as f32;
+        for (pan, tilt) in [(0.0, 0.0), (12.0, -12.0), (-45.5, 0.3), (59.0, 60.0)] {
+            let frame = Frame::pan_tilt(1, RateCommand { pan, tilt }, 60.0);
+            let rates = Frame::rates(&Decoder::default().push(&frame.encode())[0], 60.0);
+            assert!(
+                (rates.pan - pan).abs() <= step / 2.0,
+                "{} != {}",
+                rates.pan,
+                pan
+            );
+            assert!(
+                (rates.tilt - tilt).abs() <= step / 2.0,
+                "{} != {}",
+                rates.tilt,
+                tilt
+            );
+        }
+    }
+
+    #[test]
+    fn resync_after_corrupted_byte() {
+        let valid = Frame::pan_tilt(
+            1,
+            RateCommand {
+                pan: 10.0,
+                tilt: 5.0,
+            },
+            60.0,
+        );
+        let mut corrupted = Frame::pan_tilt(
+            1,
+            RateCommand {
+                pan: -20.0,
+                tilt: 0.0,
+            },
+            60.0,
+        )
+        .encode();
+        corrupted[4] ^= 0x01;
+
+        let mut bytes = vec![0x12, 0x34];
+        bytes.extend(corrupted);
+        bytes.extend(valid.encode());
+        let mut decoder = Decoder::default();
+        let frames: Vec<Frame> = bytes
+            .chunks(3)
+            .flat_map(|chunk| decoder.push(chunk))
+            .collect();
+        assert_eq!(frames, [valid]);
+        assert_eq!(decoder.checksum_errors, 1);
+    }
+
+    #[test]
+    fn actuator_skips_repeated_frames() {
+        let pty = serial::loopback().expect("Can't open pty");
+        let port = serial::open(&pty.path, 9600).expect("Can't open pty slave");
+        let mut master = pty.master;
+        let mut actuator = PelcoDActuator::new(port, 3, 60.0);
+        let commands = [
+            RateCommand {
+                pan: 20.0,
+                tilt: 0.0,
+            },
+            RateCommand {
+                pan: 20.0,
+                tilt: 0.0,
+            },
+            RateCommand {
+                pan: 0.0,
+                tilt: -30.0,
+            },
+            RateCommand::default(),
+        ];
+        for command in commands {
+            actuator.set_rates(command).expect("Can't write command");
+        }
+
+        let mut decoder = Decoder::default();
+        let mut frames = Vec::new();
+        let mut buf = [0u8; 64];
+        while frames.len() < 3 {
+            let n = master.read(&mut buf).expect("Can't read pty");
+            frames.extend(decoder.push(&buf[..n]));
+        }
+        let expected: Vec<Frame> = [commands[0], commands[2], commands[3]]
+            .into_iter()
+            .map(|command| Frame::pan_tilt(3, command, 60.0))
+            .collect();
+        assert_eq!(frames, expected);
+    }
+}
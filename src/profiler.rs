@@ -0,0 +1,374 @@
+use crate::config::ProfilingConfig;
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write as _};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Pull,
+    Map,
+    Preprocess,
+    Inference,
+    Postprocess,
+    TrackerUpdate,
+    Overlay,
+    Push,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 8] = [
+        Stage::Pull,
+        Stage::Map,
+        Stage::Preprocess,
+        Stage::Inference,
+        Stage::Postprocess,
+        Stage::TrackerUpdate,
+        Stage::Overlay,
+        Stage::Push,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Pull => "pull",
+            Stage::Map => "map",
+            Stage::Preprocess => "preprocess",
+            Stage::Inference => "inference",
+            Stage::Postprocess => "postprocess",
+            Stage::TrackerUpdate => "tracker_update",
+            Stage::Overlay => "overlay",
+            Stage::Push => "push",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct StageStats {
+    pub count: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub fps: f64,
+    pub stages: Vec<(Stage, StageStats)>,
+}
+
+impl Report {
+    /// `{"ts": ..., "fps": ..., "stages": {"<стадия>": {"count": ..., "p50_ms": ...}}}`
+    pub fn to_json(&self) -> Value {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        let stages: Map<String, Value> = self
+            .stages
+            .iter()
+            .map(|(stage, stats)| (stage.name().to_string(), json!(stats)))
+            .collect();
+        json!({ "ts": ts, "fps": self.fps, "stages": stages })
+    }
+}
+
+/// Замеры по стадиям обработки кадра в скользящем окне.
+pub struct Profiler {
+    config: ProfilingConfig,
+    started: Instant,
+    samples: Vec<VecDeque<Duration>>,
+    frames: VecDeque<Instant>,
+    last_report: Instant,
+    report_file: Option<BufWriter<File>>,
+    /// Chrome trace (chrome://tracing, Perfetto): массив событий закрывается в `drop`;
+    /// без закрывающей скобки после аварийного завершения файл просмотрщики тоже читают
+    trace_file: Option<BufWriter<File>>,
+    trace_events: usize,
+}
+
+impl Profiler {
+    pub fn new(config: &ProfilingConfig) -> Self {
+        let open = |path: &Option<String>, append: bool| {
+            let path = path.as_ref()?;
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(path)
+                .map_err(|err| warn!("Can't open {}: {}", path, err))
+                .ok()
+                .map(BufWriter::new)
+        };
+
+        let mut trace_file = if config.enabled {
+            open(&config.trace_file, false)
+        } else {
+            None
+        };
+        if let Some(trace) = trace_file.as_mut() {
+            let _ = writeln!(trace, "[");
+        }
+
+        Self {
+            config: config.clone(),
+            started: Instant::now(),
+            samples: vec![VecDeque::with_capacity(config.window); Stage::ALL.len()],
+            frames: VecDeque::with_capacity(config.window),
+            last_report: Instant::now(),
+            report_file: if config.enabled {
+                open(&config.report_file, true)
+            } else {
+                None
+            },
+            trace_file,
+            trace_events: 0,
+        }
+    }
+
+    /// Записывает длительность стадии, начавшейся в `start`.
+    pub fn record(&mut self, stage: Stage, start: Instant) {
+        if !self.config.enabled {
+            return;
+        }
+        let elapsed = start.elapsed();
+
+        let samples = &mut self.samples[stage as usize];
+        if samples.len() >= self.config.window.max(1) {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed);
+
+        if let Some(trace) = self.trace_file.as_mut() {
+            let event = json!({
+                "name": stage.name(),
+                "ph": "X",
+                "ts": start.saturating_duration_since(self.started).as_micros() as u64,
+                "dur": elapsed.as_micros() as u64,
+                "pid": 1,
+                "tid": 1,
+            });
+            let separator = if self.trace_events > 0 { ",\n" } else { "" };
+            let _ = write!(trace, "{}{}", separator, event);
+            self.trace_events += 1;
+        }
+    }
+
+    /// Отмечает конец кадра; раз в `report_interval_secs` выводит отчёт.
+    pub fn frame_done(&mut self) {
+        if !self.config.enabled {
+            return;
+        }
+
+        if self.frames.len() >= self.config.window.max(1) {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(Instant::now());
+
+        if self.last_report.elapsed().as_secs_f64() >= self.config.report_interval_secs {
+            self.last_report = Instant::now();
+            self.write_report();
+        }
+    }
+
+    pub fn report(&self) -> Report {
+        let fps = match (self.frames.front(), self.frames.back()) {
+            (Some(first), Some(last)) if self.frames.len() > 1 => {
+                let span = last.duration_since(*first).as_secs_f64();
+                if span > 0.0 {
+                    (self.frames.len() - 1) as f64 / span
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+
+        let stages = Stage::ALL
+            .iter()
+            .map(|&stage| (stage, stage_stats(&self.samples[stage as usize])))
+            .filter(|(_, stats)| stats.count > 0)
+            .collect();
+
+        Report { fps, stages }
+    }
+
+    fn write_report(&mut self) {
+        let report = self.report();
+
+        let mut text = format!("FPS {:.1}", report.fps);
+        for (stage, stats) in &report.stages {
+            let _ = write!(
+                text,
+                " | {} p50 {:.1} p95 {:.1} p99 {:.1} ms",
+                stage.name(),
+                stats.p50_ms,
+                stats.p95_ms,
+                stats.p99_ms
+            );
+        }
+        info!("{}", text);
+
+        if let Some(file) = self.report_file.as_mut() {
+            let _ = writeln!(file, "{}", report.to_json());
+            let _ = file.flush();
+        }
+        if let Some(trace) = self.trace_file.as_mut() {
+            let _ = trace.flush();
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        if let Some(trace) = self.trace_file.as_mut() {
+            let _ = writeln!(trace, "\n]");
+            let _ = trace.flush();
+        }
+    }
+}
+
+fn stage_stats(samples: &VecDeque<Duration>) -> StageStats {
+    if samples.is_empty() {
+        return StageStats::default();
+    }
+
+    let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+    ms.sort_by(|a, b| a.total_cmp(b));
+
+    StageStats {
+        count: ms.len(),
+        p50_ms: percentile(&ms, 0.50),
+        p95_ms: percentile(&ms, 0.95),
+        p99_ms: percentile(&ms, 0.99),
+    }
+}
+
+/// Перцентиль по методу ближайшего ранга; `sorted` не пустой.
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn config(window: usize) -> ProfilingConfig {
+        ProfilingConfig {
+            window,
+            report_interval_secs: f64::INFINITY,
+            ..ProfilingConfig::default()
+        }
+    }
+
+    /// Замер длительностью не меньше `ms`.
+    fn record_ms(profiler: &mut Profiler, stage: Stage, ms: u64) {
+        profiler.record(stage, Instant::now() - Duration::from_millis(ms));
+    }
+
+    #[test]
+    fn nearest_rank_percentile() {
+        let sorted: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 0.5), 5.0);
+        assert_eq!(percentile(&sorted, 0.95), 10.0);
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&[7.0], 0.99), 7.0);
+
+        let samples: VecDeque<Duration> = [30, 10, 20, 40]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect();
+        let stats = stage_stats(&samples);
+        assert_eq!(stats.count, 4);
+        assert_eq!(
+            (stats.p50_ms, stats.p95_ms, stats.p99_ms),
+            (20.0, 40.0, 40.0)
+        );
+        assert_eq!(stage_stats(&VecDeque::new()).count, 0);
+    }
+
+    #[test]
+    fn window_evicts_old_samples() {
+        let mut profiler = Profiler::new(&config(3));
+        for ms in [100, 10, 20, 30] {
+            record_ms(&mut profiler, Stage::Inference, ms);
+        }
+        record_ms(&mut profiler, Stage::Push, 1);
+
+        let report = profiler.report();
+        assert_eq!(
+            report
+                .stages
+                .iter()
+                .map(|(stage, stats)| (*stage, stats.count))
+                .collect::<Vec<_>>(),
+            [(Stage::Inference, 3), (Stage::Push, 1)]
+        );
+        // Замер в 100 мс вытеснен; замеры не короче заданных, но и не длиннее следующего
+        let inference = report.stages[0].1;
+        assert!((20.0..30.0).contains(&inference.p50_ms), "{:?}", inference);
+        assert!((30.0..100.0).contains(&inference.p99_ms), "{:?}", inference);
+
+        for _ in 0..5 {
+            profiler.frame_done();
+        }
+        assert_eq!(profiler.frames.len(), 3);
+    }
+
+    #[test]
+    fn fps_over_window() {
+        let mut profiler = Profiler::new(&config(10));
+        assert_eq!(profiler.report().fps, 0.0);
+
+        let start = Instant::now();
+        profiler.frames = (0..5)
+            .map(|i| start + Duration::from_millis(40 * i))
+            .collect();
+        assert!((profiler.report().fps - 25.0).abs() < 1e-9);
+
+        profiler.frames = VecDeque::from([start, start]);
+        assert_eq!(profiler.report().fps, 0.0);
+    }
+
+    #[test]
+    fn disabled_records_nothing() {
+        let mut profiler = Profiler::new(&ProfilingConfig {
+            enabled: false,
+            ..config(10)
+        });
+        record_ms(&mut profiler, Stage::Pull, 1);
+        profiler.frame_done();
+        assert!(profiler.report().stages.is_empty());
+        assert!(profiler.frames.is_empty());
+    }
+
+    #[test]
+    fn trace_is_closed_json_array() {
+        let path = std::env::temp_dir().join(format!("profiler-test-{}.json", std::process::id()));
+        let mut profiler = Profiler::new(&ProfilingConfig {
+            trace_file: Some(path.to_string_lossy().into_owned()),
+            ..config(10)
+        });
+        record_ms(&mut profiler, Stage::Pull, 1);
+        record_ms(&mut profiler, Stage::Inference, 2);
+        drop(profiler);
+
+        let trace: Vec<Value> = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(
+            trace
+                .iter()
+                .map(|event| event["name"].as_str().unwrap())
+                .collect::<Vec<_>>(),
+            ["pull", "inference"]
+        );
+        assert!(trace.iter().all(|event| event["ph"] == "X"));
+        assert!(trace[1]["dur"].as_u64().unwrap() >= 2000);
+    }
+}
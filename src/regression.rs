@@ -0,0 +1,188 @@
+//! Регрессионные тесты трекеров на коротких последовательностях с разметкой.
+//!
+//! Прогон тот же, что у `eval`: кадры по одному, без GStreamer и без отбрасывания
+//! по времени, поэтому результат зависит только от трекера и его настроек.
+//! Последовательности лежат в `tests/fixtures` в формате OTB и генерируются
+//! `tests/fixtures/generate.py`.
+//!
+//! Модели NanoTrack и ViT лежат в `models/`, моделей DaSiamRPN в репозитории нет:
+//! его тест помечен `#[ignore]` и запускается через `cargo test -- --ignored`.
+
+use crate::config::{TrackerKind, model_path};
+
+/// Файлы моделей, без которых трекер не создать.
+fn model_files(kind: TrackerKind) -> &'static [&'static str] {
+    match kind {
+        TrackerKind::Nano => &["nanotrack_backbone_sim.onnx", "nanotrack_head_sim.onnx"],
+        TrackerKind::Vit => &["object_tracking_vittrack_2023sep.onnx"],
+        TrackerKind::VitInt8 => &["object_tracking_vittrack_2023sep_int8bq.onnx"],
+        TrackerKind::Dasiamrpn => &[
+            "dasiamrpn_model.onnx",
+            "dasiamrpn_kernel_cls1.onnx",
+            "dasiamrpn_kernel_r1.onnx",
+        ],
+        TrackerKind::Kcf => &[],
+    }
+}
+
+pub fn models_available(kind: TrackerKind) -> bool {
+    model_files(kind)
+        .iter()
+        .all(|name| model_path(name).exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TrackersConfig;
+    use crate::eval::{DatasetFormat, Sequence, run};
+    use crate::trackers::create_tracker;
+    use opencv::prelude::*;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> Sequence {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name);
+        Sequence::load(&dir).expect("Can't load fixture").remove(0)
+    }
+
+    /// Прогон с допусками; без файлов моделей тест падает, а не проходит молча.
+    fn check(
+        primary: TrackerKind,
+        fallback: Option<TrackerKind>,
+        sequence: &str,
+        min_auc: f32,
+        min_success: f32,
+    ) {
+        if let Some(kind) = [Some(primary), fallback]
+            .into_iter()
+            .flatten()
+            .find(|&kind| !models_available(kind))
+        {
+            panic!(
+                "{} on {}: no {} models in models/ ({})",
+                primary.name(),
+                sequence,
+                kind.name(),
+                model_files(kind).join(", ")
+            );
+        }
+        let config = TrackersConfig {
+            primary,
+            fallback,
+            ..TrackersConfig::default()
+        };
+        let sequence = fixture(sequence);
+        let mut tracker = create_tracker(&config).expect("Can't create tracker");
+        let run = run(tracker.as_mut(), &sequence).expect("Tracker failed");
+
+        let (auc, success) = (run.success_auc(), run.success_rate(0.5));
+        eprintln!(
+            "{} / {:?} on {}: AUC {:.3}, success@0.5 {:.3}",
+            primary.name(),
+            fallback,
+            sequence.name,
+            auc,
+            success
+        );
+        assert!(
+            auc >= min_auc,
+            "{} on {}: AUC {:.3} < {:.3}",
+            primary.name(),
+            sequence.name,
+            auc,
+            min_auc
+        );
+        assert!(
+            success >= min_success,
+            "{} on {}: success {:.3} < {:.3}",
+            primary.name(),
+            sequence.name,
+            success,
+            min_success
+        );
+    }
+
+    #[test]
+    fn fixtures_load() {
+        for name in ["translate", "scale"] {
+            let sequence = fixture(name);
+            assert_eq!(sequence.format, DatasetFormat::Otb);
+            assert_eq!(sequence.frames.len(), sequence.groundtruth.len());
+            let frame = sequence.frame(0).expect("Can't read frame");
+            assert_eq!((frame.cols(), frame.rows()), (128, 96));
+        }
+    }
+
+    #[test]
+    fn runs_are_deterministic() {
+        let sequence = fixture("translate");
+        let mut first = create_tracker(&TrackersConfig {
+            primary: TrackerKind::Kcf,
+            fallback: None,
+            ..TrackersConfig::default()
+        })
+        .unwrap();
+        let mut second = create_tracker(&TrackersConfig {
+            primary: TrackerKind::Kcf,
+            fallback: None,
+            ..TrackersConfig::default()
+        })
+        .unwrap();
+        assert_eq!(
+            run(first.as_mut(), &sequence).unwrap().boxes,
+            run(second.as_mut(), &sequence).unwrap().boxes
+        );
+    }
+
+    // KCF не меняет масштаб, поэтому проверяется только на сдвиге
+    #[test]
+    fn kcf_translate() {
+        check(TrackerKind::Kcf, None, "translate", 0.5, 0.8);
+    }
+
+    #[test]
+    fn nano_translate() {
+        check(TrackerKind::Nano, None, "translate", 0.55, 0.9);
+    }
+
+    #[test]
+    fn nano_scale() {
+        check(TrackerKind::Nano, None, "scale", 0.5, 0.85);
+    }
+
+    #[test]
+    fn vit_translate() {
+        check(TrackerKind::Vit, None, "translate", 0.55, 0.9);
+    }
+
+    #[test]
+    fn vit_scale() {
+        check(TrackerKind::Vit, None, "scale", 0.5, 0.85);
+    }
+
+    #[test]
+    fn vit_int8_translate() {
+        check(TrackerKind::VitInt8, None, "translate", 0.5, 0.85);
+    }
+
+    #[test]
+    fn vit_int8_scale() {
+        check(TrackerKind::VitInt8, None, "scale", 0.45, 0.8);
+    }
+
+    #[test]
+    #[ignore = "needs dasiamrpn models"]
+    fn dasiamrpn_translate() {
+        check(TrackerKind::Dasiamrpn, None, "translate", 0.55, 0.9);
+    }
+
+    /// Конфигурация по умолчанию: ViT int8 с запасным ViT и порогами из конфига.
+    #[test]
+    fn default_config_scale() {
+        let config = TrackersConfig::default();
+        check(config.primary, config.fallback, "scale", 0.45, 0.8);
+    }
+}
@@ -0,0 +1,354 @@
+//! SimpleBGC serial API v1: `>`, команда, длина, сумма заголовка, данные, сумма данных.
+
+use crate::gimbal::{Actuator, RateCommand, SimActuator};
+use log::debug;
+use std::fs::File;
+use std::io::{self, Read as _, Write as _};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const START: u8 = b'>';
+pub const CMD_CONTROL: u8 = 67;
+pub const CMD_GET_ANGLES: u8 = 73;
+
+const MODE_SPEED: u8 = 1;
+/// Единица скорости в CMD_CONTROL, град/с
+pub const SPEED_UNIT: f32 = 0.122_074_04;
+/// Единица угла, градусы
+pub const ANGLE_UNIT: f32 = 0.021_972_656;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub command: u8,
+    pub data: Vec<u8>,
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+pub fn encode(command: u8, data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(data.len() + 5);
+    frame.extend_from_slice(&[START, command, data.len() as u8]);
+    frame.push(checksum(&frame[1..3]));
+    frame.extend_from_slice(data);
+    frame.push(checksum(data));
+    frame
+}
+
+/// CMD_CONTROL в режиме скорости: крен не трогаем, pitch — tilt, yaw — pan.
+pub fn control_speed(command: RateCommand) -> Vec<u8> {
+    let speed = |rate: f32| {
+        ((rate / SPEED_UNIT).round() as i32).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+    };
+    let mut data = vec![MODE_SPEED];
+    for axis_speed in [0, speed(command.tilt), speed(command.pan)] {
+        data.extend_from_slice(&axis_speed.to_le_bytes());
+        data.extend_from_slice(&0i16.to_le_bytes());
+    }
+    encode(CMD_CONTROL, &data)
+}
+
+/// Скорости из CMD_CONTROL в режиме скорости, как их видит плата.
+pub fn parse_control(data: &[u8]) -> Option<RateCommand> {
+    if data.len() < 13 || data[0] != MODE_SPEED {
+        return None;
+    }
+    let speed = |axis: usize| {
+        i16::from_le_bytes([data[1 + axis * 4], data[2 + axis * 4]]) as f32 * SPEED_UNIT
+    };
+    Some(RateCommand {
+        pan: speed(2),
+        tilt: speed(1),
+    })
+}
+
+/// Ответ на CMD_GET_ANGLES с углами IMU; целевые углы и скорости нулевые.
+pub fn angles_reply(pan: f32, tilt: f32) -> Vec<u8> {
+    let angle = |deg: f32| ((deg / ANGLE_UNIT).round() as i32).clamp(-32768, 32767) as i16;
+    let mut data = Vec::with_capacity(18);
+    for imu in [0, angle(tilt), angle(pan)] {
+        data.extend_from_slice(&imu.to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+    }
+    encode(CMD_GET_ANGLES, &data)
+}
+
+/// Углы pan/tilt (yaw/pitch по IMU) из ответа на CMD_GET_ANGLES.
+pub fn parse_angles(data: &[u8]) -> Option<(f32, f32)> {
+    if data.len() < 18 {
+        return None;
+    }
+    // На каждую ось (roll, pitch, yaw): угол IMU, целевой угол, целевая скорость
+    let imu = |axis: usize| i16::from_le_bytes([data[axis * 6], data[axis * 6 + 1]]) as f32;
+    Some((imu(2) * ANGLE_UNIT, imu(1) * ANGLE_UNIT))
+}
+
+/// Потоковый декодер; кадры с неверными суммами пропускаются.
+#[derive(Default)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+    pub checksum_errors: u64,
+}
+
+impl Decoder {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Frame> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+
+        loop {
+            match self.buffer.iter().position(|&b| b == START) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    self.buffer.clear();
+                    break;
+                }
+            }
+            if self.buffer.len() < 4 {
+                break;
+            }
+            if checksum(&self.buffer[1..3]) != self.buffer[3] {
+                self.checksum_errors += 1;
+                self.buffer.drain(..1);
+                continue;
+            }
+            let len = self.buffer[2] as usize;
+            if self.buffer.len() < len + 5 {
+                break;
+            }
+            let data = &self.buffer[4..4 + len];
+            if checksum(data) != self.buffer[4 + len] {
+                self.checksum_errors += 1;
+                self.buffer.drain(..1);
+                continue;
+            }
+            frames.push(Frame {
+                command: self.buffer[1],
+                data: data.to_vec(),
+            });
+            self.buffer.drain(..len + 5);
+        }
+        frames
+    }
+}
+
+/// Плата на стороне псевдотерминала: команды скорости двигают модель привода,
+/// на запрос углов отвечает её положением.
+pub fn emulate(mut master: File, lag_secs: f32) {
+    let mut board = SimActuator::new(lag_secs);
+    let mut decoder = Decoder::default();
+    let mut buf = [0u8; 256];
+    loop {
+        let n = match master.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        for frame in decoder.push(&buf[..n]) {
+            match frame.command {
+                CMD_CONTROL => {
+                    if let Some(command) = parse_control(&frame.data) {
+                        let _ = board.set_rates(command);
+                    }
+                }
+                CMD_GET_ANGLES => {
+                    board.sync();
+                    if master
+                        .write_all(&angles_reply(board.pan, board.tilt))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+                command => debug!("SBGC emulator: unknown command {}", command),
+            }
+        }
+    }
+}
+
+/// Контроллер SimpleBGC; углы читаются фоновым потоком из ответов платы.
+pub struct SbgcActuator {
+    port: File,
+    angles: Arc<Mutex<Option<(f32, f32)>>>,
+    last_request: Option<Instant>,
+}
+
+/// Как часто запрашивать углы
+const ANGLES_INTERVAL: Duration = Duration::from_millis(50);
+
+impl SbgcActuator {
+    pub fn new(port: File) -> io::Result<Self> {
+        let angles = Arc::new(Mutex::new(None));
+        let mut reader = port.try_clone()?;
+        let shared = angles.clone();
+        std::thread::spawn(move || {
+            let mut decoder = Decoder::default();
+            let mut buf = [0u8; 256];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) => continue,
+                    Ok(n) => {
+                        for frame in decoder.push(&buf[..n]) {
+                            match frame.command {
+                                CMD_GET_ANGLES => {
+                                    *shared.lock().unwrap() = parse_angles(&frame.data);
+                                }
+                                command => debug!("SBGC reply {}: {:?}", command, frame.data),
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        debug!("SBGC port closed: {}", err);
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            port,
+            angles,
+            last_request: None,
+        })
+    }
+}
+
+impl Actuator for SbgcActuator {
+    fn set_rates(&mut self, command: RateCommand) -> io::Result<()> {
+        self.port.write_all(&control_speed(command))?;
+        if self
+            .last_request
+            .is_none_or(|at| at.elapsed() >= ANGLES_INTERVAL)
+        {
+            self.last_request = Some(Instant::now());
+            self.port.write_all(&encode(CMD_GET_ANGLES, &[]))?;
+        }
+        Ok(())
+    }
+
+    fn angles(&mut self) -> Option<(f32, f32)> {
+        *self.angles.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial;
+
+    fn decode_one(bytes: &[u8]) -> Frame {
+        let mut frames = Decoder::default().push(bytes);
+        assert_eq!(frames.len(), 1);
+        frames.remove(0)
+    }
+
+    #[test]
+    fn frame_checksums() {
+        assert_eq!(
+            encode(CMD_GET_ANGLES, &[]),
+            [START, CMD_GET_ANGLES, 0, CMD_GET_ANGLES, 0]
+        );
+        // Суммы заголовка и данных считаются отдельно, по модулю 256
+        assert_eq!(
+            encode(CMD_CONTROL, &[0xF0, 0x20]),
+            [START, CMD_CONTROL, 2, CMD_CONTROL + 2, 0xF0, 0x20, 0x10]
+        );
+    }
+
+    #[test]
+    fn control_round_trip() {
+        for (pan, tilt) in [(0.0, 0.0), (30.0, -12.5), (-90.0, 45.0)] {
+            let frame = decode_one(&control_speed(RateCommand { pan, tilt }));
+            assert_eq!(frame.command, CMD_CONTROL);
+            let parsed = parse_control(&frame.data).expect("Not a speed command");
+            assert!(
+                (parsed.pan - pan).abs() <= SPEED_UNIT / 2.0,
+                "{} != {}",
+                parsed.pan,
+                pan
+            );
+            assert!(
+                (parsed.tilt - tilt).abs() <= SPEED_UNIT / 2.0,
+                "{} != {}",
+                parsed.tilt,
+                tilt
+            );
+        }
+        assert_eq!(parse_control(&[MODE_SPEED, 0, 0]), None);
+    }
+
+    #[test]
+    fn angles_round_trip() {
+        for (pan, tilt) in [(0.0, 0.0), (123.4, -45.6), (-179.9, 89.9)] {
+            let frame = decode_one(&angles_reply(pan, tilt));
+            assert_eq!(frame.command, CMD_GET_ANGLES);
+            let (parsed_pan, parsed_tilt) = parse_angles(&frame.data).expect("Short reply");
+            assert!(
+                (parsed_pan - pan).abs() <= ANGLE_UNIT / 2.0,
+                "{} != {}",
+                parsed_pan,
+                pan
+            );
+            assert!(
+                (parsed_tilt - tilt).abs() <= ANGLE_UNIT / 2.0,
+                "{} != {}",
+                parsed_tilt,
+                tilt
+            );
+        }
+    }
+
+    #[test]
+    fn resync_after_corrupted_byte() {
+        let valid = angles_reply(10.0, -5.0);
+        let mut corrupted = control_speed(RateCommand {
+            pan: 20.0,
+            tilt: 0.0,
+        });
+        corrupted[6] ^= 0x01;
+
+        let mut bytes = vec![0x00, 0x13];
+        bytes.extend(&corrupted);
+        bytes.extend(&valid);
+        let mut decoder = Decoder::default();
+        let frames: Vec<Frame> = bytes
+            .chunks(3)
+            .flat_map(|chunk| decoder.push(chunk))
+            .collect();
+        assert_eq!(frames, [decode_one(&valid)]);
+        assert_eq!(decoder.checksum_errors, 1);
+    }
+
+    #[test]
+    fn actuator_drives_emulator() {
+        let pty = serial::loopback().expect("Can't open pty");
+        let port = serial::open(&pty.path, 115200).expect("Can't open pty slave");
+        let master = pty.master;
+        std::thread::spawn(move || emulate(master, 0.0));
+
+        let mut actuator = SbgcActuator::new(port).expect("Can't start actuator");
+        let command = RateCommand {
+            pan: 40.0,
+            tilt: -20.0,
+        };
+        let started = Instant::now();
+        // Каждый вызов после ANGLES_INTERVAL ещё и запрашивает углы
+        let angles = loop {
+            actuator.set_rates(command).expect("Can't write command");
+            if let Some((pan, tilt)) = actuator
+                .angles()
+                .filter(|&(pan, tilt)| pan > 1.0 && tilt < -0.5)
+            {
+                break (pan, tilt);
+            }
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "No angles from emulator: {:?}",
+                actuator.angles()
+            );
+            std::thread::sleep(ANGLES_INTERVAL);
+        };
+        // Скорости в два раза отличаются, значит и углы тоже
+        assert!((angles.0 / angles.1 + 2.0).abs() < 0.2, "{:?}", angles);
+    }
+}
@@ -0,0 +1,108 @@
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::fs::OpenOptionsExt;
+
+fn baud_constant(baud: u32) -> io::Result<libc::speed_t> {
+    Ok(match baud {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        921600 => libc::B921600,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported baud rate {}", baud),
+            ));
+        }
+    })
+}
+
+/// Открывает последовательный порт в raw-режиме 8N1 без управления потоком.
+pub fn open(path: &str, baud: u32) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(path)?;
+    let speed = baud_constant(baud)?;
+
+    unsafe {
+        let fd = file.as_raw_fd();
+        let mut tty: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut tty) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        libc::cfmakeraw(&mut tty);
+        tty.c_cflag &= !(libc::CSTOPB | libc::CRTSCTS | libc::PARENB);
+        tty.c_cflag |= libc::CLOCAL | libc::CREAD | libc::CS8;
+        // Чтение возвращается через 0.1 с даже без данных
+        tty.c_cc[libc::VMIN] = 0;
+        tty.c_cc[libc::VTIME] = 1;
+        if libc::cfsetispeed(&mut tty, speed) != 0
+            || libc::cfsetospeed(&mut tty, speed) != 0
+            || libc::tcsetattr(fd, libc::TCSANOW, &tty) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(file)
+}
+
+/// Псевдотерминал вместо устройства: привод открывает `path` как обычный порт,
+/// а байты читаются и пишутся через `master`.
+pub struct Loopback {
+    pub master: File,
+    pub path: String,
+}
+
+pub fn loopback() -> io::Result<Loopback> {
+    unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let master = File::from_raw_fd(fd);
+        if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut name = [0 as libc::c_char; 128];
+        if libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let path = CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned();
+        Ok(Loopback { master, path })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read as _, Write as _};
+
+    #[test]
+    fn loopback_passes_raw_bytes() {
+        let mut pty = loopback().expect("Can't open pty");
+        let mut port = open(&pty.path, 115200).expect("Can't open pty slave");
+        // В raw-режиме перевод строки и управляющие символы идут как есть
+        let sent = [0xFF, 0x0A, 0x0D, 0x03, 0x00, 0x3E];
+
+        port.write_all(&sent).unwrap();
+        let mut received = [0u8; 6];
+        pty.master.read_exact(&mut received).unwrap();
+        assert_eq!(received, sent);
+
+        pty.master.write_all(&sent).unwrap();
+        let mut received = [0u8; 6];
+        port.read_exact(&mut received).unwrap();
+        assert_eq!(received, sent);
+
+        assert!(open(&pty.path, 12345).is_err());
+    }
+}
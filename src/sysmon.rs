@@ -0,0 +1,399 @@
+use crate::config::ThermalConfig;
+use crate::thermal::{CpuFreq, ThermalGovernor, ThermalZone, max_temp, read_cpufreq, read_thermal_zones};
+use crate::utils::get_mem_usage;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Счётчики одной строки `cpu`/`cpuN` из `/proc/stat`, в тиках USER_HZ.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub busy: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Загрузка в процентах между двумя снимками.
+    pub fn usage_since(&self, prev: &CpuTimes) -> f32 {
+        let total = self.total.saturating_sub(prev.total);
+        if total == 0 {
+            return 0.0;
+        }
+        self.busy.saturating_sub(prev.busy) as f32 * 100.0 / total as f32
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcStat {
+    pub total: CpuTimes,
+    pub cores: Vec<CpuTimes>,
+}
+
+/// Разбирает `/proc/stat`: агрегированную строку `cpu` и строки `cpuN`.
+pub fn parse_proc_stat(text: &str) -> ProcStat {
+    let mut stat = ProcStat::default();
+
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(name) = fields.next() else {
+            continue;
+        };
+        if !name.starts_with("cpu") {
+            continue;
+        }
+
+        // user nice system idle iowait irq softirq steal guest guest_nice
+        let values: Vec<u64> = fields.map(|v| v.parse().unwrap_or(0)).collect();
+        let idle = values.get(3).copied().unwrap_or(0) + values.get(4).copied().unwrap_or(0);
+        // guest и guest_nice уже учтены в user и nice
+        let total: u64 = values.iter().take(8).sum();
+        let times = CpuTimes {
+            busy: total.saturating_sub(idle),
+            total,
+        };
+
+        if name == "cpu" {
+            stat.total = times;
+        } else {
+            stat.cores.push(times);
+        }
+    }
+
+    stat
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessTimes {
+    /// utime + stime, тики USER_HZ
+    pub cpu_ticks: u64,
+    pub rss_pages: u64,
+}
+
+/// Разбирает `/proc/self/stat`. Имя процесса в скобках может содержать
+/// пробелы и скобки, поэтому поля считаются от последней `)`.
+pub fn parse_process_stat(text: &str) -> Option<ProcessTimes> {
+    let rest = &text[text.rfind(')')? + 1..];
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    // После имени: state(3) ... utime(14) stime(15) ... rss(24), нумерация с 1 по proc(5)
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+
+    Some(ProcessTimes {
+        cpu_ticks: field(14)? + field(15)?,
+        rss_pages: field(24)?,
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CpuStats {
+    /// Загрузка всех ядер, %
+    pub total: f32,
+    pub per_core: Vec<f32>,
+    pub cores: usize,
+    /// Доля процесса от всей мощности машины, %
+    pub process_cpu: f32,
+    pub process_rss_bytes: u64,
+}
+
+/// Загрузка CPU по разнице снимков `/proc/stat` и `/proc/self/stat`.
+///
+/// Корень файловой системы задаётся явно, чтобы можно было подложить
+/// файлы-фикстуры вместо настоящего procfs.
+pub struct SystemMonitor {
+    root: PathBuf,
+    page_size: u64,
+    prev: Option<(ProcStat, ProcessTimes)>,
+}
+
+impl SystemMonitor {
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    pub fn with_root(root: impl AsRef<Path>) -> Self {
+        // SAFETY: sysconf без побочных эффектов
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Self {
+            root: root.as_ref().to_path_buf(),
+            page_size: if page_size > 0 {
+                page_size as u64
+            } else {
+                4096
+            },
+            prev: None,
+        }
+    }
+
+    fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.root.join(path)).unwrap_or_default()
+    }
+
+    /// Делает новый снимок и считает загрузку относительно предыдущего.
+    /// Первый вызов возвращает загрузку с момента загрузки системы.
+    pub fn sample(&mut self) -> CpuStats {
+        let stat = parse_proc_stat(&self.read("proc/stat"));
+        let process = parse_process_stat(&self.read("proc/self/stat")).unwrap_or_default();
+
+        let (prev_stat, prev_process) = self.prev.take().unwrap_or_default();
+
+        let per_core = stat
+            .cores
+            .iter()
+            .enumerate()
+            .map(|(i, core)| core.usage_since(&prev_stat.cores.get(i).copied().unwrap_or_default()))
+            .collect();
+
+        let total_ticks = stat.total.total.saturating_sub(prev_stat.total.total);
+        let process_cpu = if total_ticks > 0 {
+            process.cpu_ticks.saturating_sub(prev_process.cpu_ticks) as f32 * 100.0
+                / total_ticks as f32
+        } else {
+            0.0
+        };
+
+        let stats = CpuStats {
+            total: stat.total.usage_since(&prev_stat.total),
+            per_core,
+            cores: stat.cores.len(),
+            process_cpu,
+            process_rss_bytes: process.rss_pages * self.page_size,
+        };
+
+        self.prev = Some((stat, process));
+        stats
+    }
+
+    /// Занятая память, %; `None`, если `proc/meminfo` не прочитать.
+    pub fn mem_usage(&self) -> Option<f32> {
+        get_mem_usage(&self.root)
+    }
+}
+
+/// `f32`, который можно читать и писать из разных потоков без блокировок.
+#[derive(Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+pub struct ZoneTemp {
+    pub index: usize,
+    pub kind: String,
+    pub temp_c: AtomicF32,
+}
+
+pub struct CoreFreq {
+    pub cpu: usize,
+    pub governor: String,
+    pub cur_khz: AtomicU64,
+    pub max_khz: AtomicU64,
+}
+
+/// Последние показания системы, обновляемые фоновым потоком.
+///
+/// Состав ядер, термозон и cpufreq фиксируется при старте, поэтому
+/// значения лежат в атомиках и читаются без блокировок.
+pub struct SystemStats {
+    pub cpu: AtomicF32,
+    pub per_core: Vec<AtomicF32>,
+    pub process_cpu: AtomicF32,
+    pub process_rss_bytes: AtomicU64,
+    pub mem: AtomicF32,
+    /// Самая горячая термозона
+    pub temp: AtomicF32,
+    pub zones: Vec<ZoneTemp>,
+    pub freqs: Vec<CoreFreq>,
+    /// Уровень троттлинга, выбранный [`ThermalGovernor`] по этим показаниям
+    pub thermal_level: AtomicUsize,
+}
+
+impl SystemStats {
+    fn new(cores: usize, zones: &[ThermalZone], freqs: &[CpuFreq]) -> Self {
+        Self {
+            cpu: AtomicF32::default(),
+            per_core: (0..cores).map(|_| AtomicF32::default()).collect(),
+            process_cpu: AtomicF32::default(),
+            process_rss_bytes: AtomicU64::new(0),
+            mem: AtomicF32::default(),
+            temp: AtomicF32::default(),
+            zones: zones
+                .iter()
+                .map(|zone| ZoneTemp {
+                    index: zone.index,
+                    kind: zone.kind.clone(),
+                    temp_c: AtomicF32::default(),
+                })
+                .collect(),
+            freqs: freqs
+                .iter()
+                .map(|freq| CoreFreq {
+                    cpu: freq.cpu,
+                    governor: freq.governor.clone(),
+                    cur_khz: AtomicU64::new(0),
+                    max_khz: AtomicU64::new(0),
+                })
+                .collect(),
+            thermal_level: AtomicUsize::new(0),
+        }
+    }
+
+    fn store(&self, cpu: &CpuStats, mem: Option<f32>, zones: &[ThermalZone], freqs: &[CpuFreq]) {
+        self.cpu.store(cpu.total);
+        for (slot, usage) in self.per_core.iter().zip(&cpu.per_core) {
+            slot.store(*usage);
+        }
+        self.process_cpu.store(cpu.process_cpu);
+        self.process_rss_bytes
+            .store(cpu.process_rss_bytes, Ordering::Relaxed);
+        // Без meminfo остаётся прошлое значение
+        if let Some(mem) = mem {
+            self.mem.store(mem);
+        }
+        self.temp.store(max_temp(zones));
+
+        // Зоны и ядра могут пропасть (выключенный датчик, offline-ядро) — сверяем по номеру
+        for slot in &self.zones {
+            if let Some(zone) = zones.iter().find(|zone| zone.index == slot.index) {
+                slot.temp_c.store(zone.temp_c);
+            }
+        }
+        for slot in &self.freqs {
+            if let Some(freq) = freqs.iter().find(|freq| freq.cpu == slot.cpu) {
+                slot.cur_khz.store(freq.cur_khz, Ordering::Relaxed);
+                slot.max_khz.store(freq.max_khz, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Запускает поток, который раз в `interval` обновляет [`SystemStats`]
+/// и по тем же показаниям термозон пересчитывает уровень троттлинга.
+/// Первые значения доступны сразу после возврата.
+pub fn spawn_sampler(interval: Duration, thermal: &ThermalConfig) -> Arc<SystemStats> {
+    let root = Path::new("/");
+    let mut monitor = SystemMonitor::new();
+    let mut governor = ThermalGovernor::new(thermal);
+
+    let cpu = monitor.sample();
+    let zones = read_thermal_zones(root);
+    let freqs = read_cpufreq(root);
+    let stats = Arc::new(SystemStats::new(cpu.cores, &zones, &freqs));
+    stats.store(&cpu, monitor.mem_usage(), &zones, &freqs);
+    if let Some(throttle) = governor.observe(&zones, &freqs) {
+        stats.thermal_level.store(throttle.level, Ordering::Relaxed);
+    }
+
+    let shared = stats.clone();
+    std::thread::Builder::new()
+        .name("sysmon".to_string())
+        .spawn(move || {
+            loop {
+                std::thread::sleep(interval);
+                let cpu = monitor.sample();
+                let zones = read_thermal_zones(root);
+                let freqs = read_cpufreq(root);
+                shared.store(&cpu, monitor.mem_usage(), &zones, &freqs);
+                if let Some(throttle) = governor.observe(&zones, &freqs) {
+                    shared.thermal_level.store(throttle.level, Ordering::Relaxed);
+                }
+            }
+        })
+        .expect("Can't spawn sysmon thread");
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+    }
+
+    fn fixture(name: &str) -> String {
+        fs::read_to_string(fixtures().join("proc").join(name)).expect("Can't read fixture")
+    }
+
+    fn times(busy: u64, total: u64) -> CpuTimes {
+        CpuTimes { busy, total }
+    }
+
+    #[test]
+    fn proc_stat() {
+        let stat = parse_proc_stat(&fixture("stat"));
+        assert_eq!(stat.total, times(10915, 89415));
+        // iowait считается простоем, guest уже входит в user
+        assert_eq!(
+            stat.cores,
+            [
+                times(2540, 22640),
+                times(2770, 21970),
+                times(2315, 23365),
+                times(3290, 21440),
+            ]
+        );
+        assert_eq!(parse_proc_stat("intr 1 2 3\nctxt 4\n"), ProcStat::default());
+    }
+
+    #[test]
+    fn process_stat() {
+        // Имя процесса с пробелами и скобками
+        assert_eq!(
+            parse_process_stat(&fixture("self/stat")),
+            Some(ProcessTimes {
+                cpu_ticks: 400,
+                rss_pages: 20000
+            })
+        );
+        assert_eq!(parse_process_stat("4242 (short) S 1 2 3"), None);
+        assert_eq!(parse_process_stat(""), None);
+    }
+
+    #[test]
+    fn cpu_usage_between_samples() {
+        let root = std::env::temp_dir().join(format!("sysmon-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("proc/self")).unwrap();
+        let snapshot = |suffix: &str| {
+            for name in ["stat", "self/stat"] {
+                fs::copy(
+                    fixtures().join("proc").join(format!("{}{}", name, suffix)),
+                    root.join("proc").join(name),
+                )
+                .unwrap();
+            }
+        };
+
+        let mut monitor = SystemMonitor::with_root(&root);
+        snapshot("");
+        let first = monitor.sample();
+        assert_eq!(first.cores, 4);
+        assert!((first.total - 10915.0 * 100.0 / 89415.0).abs() < 1e-3);
+
+        snapshot(".next");
+        let second = monitor.sample();
+        assert_eq!(second.per_core, [50.0, 0.0, 80.0, 100.0]);
+        assert_eq!(second.total, 57.5);
+        assert_eq!(second.process_cpu, 25.0);
+        assert_eq!(second.process_rss_bytes, 20480 * monitor.page_size);
+
+        // Без meminfo памяти не видно, но CPU считается
+        assert_eq!(monitor.mem_usage(), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn mem_usage() {
+        assert_eq!(SystemMonitor::with_root(fixtures()).mem_usage(), Some(75.0));
+    }
+}
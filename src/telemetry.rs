@@ -0,0 +1,560 @@
+//! Покадровая телеметрия трекинга: UDP и/или файл, в JSON или компактном бинарном виде.
+//!
+//! Бинарная запись версии 1, little-endian, без выравнивания:
+//!
+//! | поле            | тип      | примечание                                    |
+//! |-----------------|----------|-----------------------------------------------|
+//! | magic           | `[u8;4]` | `NPGT`                                        |
+//! | version         | u16      | [`SCHEMA_VERSION`]                            |
+//! | state           | u8       | [`TrackState`]                                |
+//! | source          | u8       | трекер, 255 — нет цели                        |
+//! | frame_index     | u64      |                                               |
+//! | pts_ns          | u64      | `u64::MAX` — у буфера нет PTS                 |
+//! | timestamp_us    | u64      | время UNIX                                    |
+//! | track_id        | u64      | 0 — нет цели                                  |
+//! | bbox            | i32 × 4  | x, y, ширина, высота; нули без цели           |
+//! | score           | f32      |                                               |
+//! | frame_us        | u32      | от получения кадра до отправки записи         |
+//! | detector_us     | u32      | 0 — детектор не запускался                    |
+//! | tracker_us      | u32      |                                               |
+//! | detection_count | u16      |                                               |
+//! | detections      | ...      | на каждую: i32 × 4 рамка, u16 класс, f32 conf |
+//!
+//! JSON — одна запись на строку с теми же полями и `"version"`.
+
+use crate::config::{TelemetryConfig, TelemetryFormat, TrackerKind};
+use crate::utils::BBox;
+use log::{info, warn};
+use opencv::core::Rect;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write as _};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+pub const SCHEMA_VERSION: u16 = 1;
+pub const MAGIC: [u8; 4] = *b"NPGT";
+/// Больше детекций в запись не попадает, чтобы датаграмма оставалась небольшой
+pub const MAX_DETECTIONS: usize = 64;
+
+const HEADER_LEN: usize = 4 + 2 + 1 + 1 + 8 * 4 + 4 * 4 + 4 + 4 * 3 + 2;
+const DETECTION_LEN: usize = 4 * 4 + 2 + 4;
+const NO_SOURCE: u8 = u8::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackState {
+    Searching = 0,
+    Tracking = 1,
+    /// Трекер потерял цель на этом кадре
+    Lost = 2,
+    /// Оператор сбросил цель и автозахват ждёт его выбора
+    Hold = 3,
+}
+
+impl TrackState {
+    #[cfg(test)]
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(TrackState::Searching),
+            1 => Some(TrackState::Tracking),
+            2 => Some(TrackState::Lost),
+            3 => Some(TrackState::Hold),
+            _ => None,
+        }
+    }
+}
+
+/// Коды трекеров в бинарной записи; порядок менять нельзя.
+const SOURCES: [TrackerKind; 5] = [
+    TrackerKind::Nano,
+    TrackerKind::Vit,
+    TrackerKind::VitInt8,
+    TrackerKind::Dasiamrpn,
+    TrackerKind::Kcf,
+];
+
+fn source_code(kind: TrackerKind) -> u8 {
+    SOURCES.iter().position(|&k| k == kind).unwrap() as u8
+}
+
+/// Рамка в JSON — `[x, y, ширина, высота]`.
+mod rect_array {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(rect: &Rect, serializer: S) -> Result<S::Ok, S::Error> {
+        [rect.x, rect.y, rect.width, rect.height].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rect, D::Error> {
+        let [x, y, width, height] = <[i32; 4]>::deserialize(deserializer)?;
+        Ok(Rect::new(x, y, width, height))
+    }
+}
+
+/// Задержки в JSON — целые микросекунды, как в бинарной записи.
+mod duration_us {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        micros(*duration).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u32::deserialize(deserializer).map(|us| Duration::from_micros(us as u64))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Target {
+    pub track_id: u64,
+    #[serde(with = "rect_array")]
+    pub bbox: Rect,
+    pub score: f32,
+    pub source: TrackerKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Detection {
+    #[serde(with = "rect_array")]
+    pub bbox: Rect,
+    pub class_id: u16,
+    pub confidence: f32,
+}
+
+impl From<&BBox> for Detection {
+    fn from(bbox: &BBox) -> Self {
+        Self {
+            bbox: bbox.rect(),
+            class_id: bbox.class_id.min(u16::MAX as usize) as u16,
+            confidence: bbox.confidence,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Latency {
+    #[serde(with = "duration_us")]
+    pub frame: Duration,
+    #[serde(with = "duration_us")]
+    pub detector: Duration,
+    #[serde(with = "duration_us")]
+    pub tracker: Duration,
+}
+
+/// Результат обработки одного кадра.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "RecordJson", try_from = "RecordJson")]
+pub struct Record {
+    pub frame_index: u64,
+    pub pts_ns: Option<u64>,
+    pub timestamp_us: u64,
+    pub state: TrackState,
+    pub target: Option<Target>,
+    pub detections: Vec<Detection>,
+    pub latency: Latency,
+}
+
+fn micros(duration: Duration) -> u32 {
+    duration.as_micros().min(u32::MAX as u128) as u32
+}
+
+impl Record {
+    pub fn to_binary(&self) -> Vec<u8> {
+        let detections = &self.detections[..self.detections.len().min(MAX_DETECTIONS)];
+        let mut out = Vec::with_capacity(HEADER_LEN + DETECTION_LEN * detections.len());
+
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+        out.push(self.state as u8);
+        out.push(self.target.map_or(NO_SOURCE, |t| source_code(t.source)));
+        out.extend_from_slice(&self.frame_index.to_le_bytes());
+        out.extend_from_slice(&self.pts_ns.unwrap_or(u64::MAX).to_le_bytes());
+        out.extend_from_slice(&self.timestamp_us.to_le_bytes());
+        out.extend_from_slice(&self.target.map_or(0, |t| t.track_id).to_le_bytes());
+        let bbox = self.target.map_or(Rect::default(), |t| t.bbox);
+        for v in [bbox.x, bbox.y, bbox.width, bbox.height] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.extend_from_slice(&self.target.map_or(0.0, |t| t.score).to_le_bytes());
+        for latency in [
+            self.latency.frame,
+            self.latency.detector,
+            self.latency.tracker,
+        ] {
+            out.extend_from_slice(&micros(latency).to_le_bytes());
+        }
+
+        out.extend_from_slice(&(detections.len() as u16).to_le_bytes());
+        for d in detections {
+            for v in [d.bbox.x, d.bbox.y, d.bbox.width, d.bbox.height] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            out.extend_from_slice(&d.class_id.to_le_bytes());
+            out.extend_from_slice(&d.confidence.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Can't serialize record")
+    }
+}
+
+/// JSON-вид [`Record`]: поля бинарной записи и версия схемы.
+#[derive(Serialize, Deserialize)]
+struct RecordJson {
+    version: u16,
+    frame: u64,
+    pts_ns: Option<u64>,
+    timestamp_us: u64,
+    state: TrackState,
+    target: Option<Target>,
+    detections: Vec<Detection>,
+    latency_us: Latency,
+}
+
+impl From<Record> for RecordJson {
+    fn from(mut record: Record) -> Self {
+        record.detections.truncate(MAX_DETECTIONS);
+        Self {
+            version: SCHEMA_VERSION,
+            frame: record.frame_index,
+            pts_ns: record.pts_ns,
+            timestamp_us: record.timestamp_us,
+            state: record.state,
+            target: record.target,
+            detections: record.detections,
+            latency_us: record.latency,
+        }
+    }
+}
+
+impl TryFrom<RecordJson> for Record {
+    type Error = String;
+
+    fn try_from(json: RecordJson) -> Result<Self, Self::Error> {
+        if json.version != SCHEMA_VERSION {
+            return Err(format!("Unsupported schema version {}", json.version));
+        }
+        Ok(Self {
+            frame_index: json.frame,
+            pts_ns: json.pts_ns,
+            timestamp_us: json.timestamp_us,
+            state: json.state,
+            target: json.target,
+            detections: json.detections,
+            latency: json.latency_us,
+        })
+    }
+}
+
+#[cfg(test)]
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+#[cfg(test)]
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let chunk = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or("Truncated record")?;
+        self.pos += N;
+        Ok(chunk.try_into().unwrap())
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        self.take().map(i32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        self.take().map(f32::from_le_bytes)
+    }
+
+    fn rect(&mut self) -> Result<Rect, String> {
+        Ok(Rect::new(
+            self.i32()?,
+            self.i32()?,
+            self.i32()?,
+            self.i32()?,
+        ))
+    }
+
+    fn latency(&mut self) -> Result<Duration, String> {
+        self.u32().map(|us| Duration::from_micros(us as u64))
+    }
+}
+
+/// Разбирает бинарную запись; возвращает её и число прочитанных байт,
+/// так что файл из записей подряд читается без разделителей.
+///
+/// Сам конвейер записи не читает: это эталон формата для тестов и получателей.
+#[cfg(test)]
+pub fn decode_binary(bytes: &[u8]) -> Result<(Record, usize), String> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take::<4>()? != MAGIC {
+        return Err("Bad magic".to_string());
+    }
+    let version = r.u16()?;
+    if version != SCHEMA_VERSION {
+        return Err(format!("Unsupported schema version {}", version));
+    }
+    let [state, source] = r.take::<2>()?;
+    let state = TrackState::from_code(state).ok_or_else(|| format!("Bad state {}", state))?;
+    let frame_index = r.u64()?;
+    let pts_ns = Some(r.u64()?).filter(|&pts| pts != u64::MAX);
+    let timestamp_us = r.u64()?;
+    let track_id = r.u64()?;
+    let bbox = r.rect()?;
+    let score = r.f32()?;
+    let latency = Latency {
+        frame: r.latency()?,
+        detector: r.latency()?,
+        tracker: r.latency()?,
+    };
+
+    let target = match source {
+        NO_SOURCE => None,
+        code => Some(Target {
+            track_id,
+            bbox,
+            score,
+            source: *SOURCES
+                .get(code as usize)
+                .ok_or_else(|| format!("Bad tracker source {}", code))?,
+        }),
+    };
+
+    let count = r.u16()?;
+    let mut detections = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        detections.push(Detection {
+            bbox: r.rect()?,
+            class_id: r.u16()?,
+            confidence: r.f32()?,
+        });
+    }
+
+    let record = Record {
+        frame_index,
+        pts_ns,
+        timestamp_us,
+        state,
+        target,
+        detections,
+        latency,
+    };
+    Ok((record, r.pos))
+}
+
+fn encode(record: &Record, format: TelemetryFormat) -> Vec<u8> {
+    match format {
+        TelemetryFormat::Binary => record.to_binary(),
+        TelemetryFormat::Json => (record.to_json() + "\n").into_bytes(),
+    }
+}
+
+/// Отправитель телеметрии; ошибки отправки не останавливают обработку кадров.
+pub struct Telemetry {
+    udp: Option<UdpSocket>,
+    udp_format: TelemetryFormat,
+    file: Option<BufWriter<File>>,
+    file_format: TelemetryFormat,
+}
+
+impl Telemetry {
+    /// `None`, если телеметрия выключена или ни один выход не открылся.
+    pub fn new(config: &TelemetryConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let udp = config.udp.as_ref().and_then(|target| {
+            let socket = UdpSocket::bind("0.0.0.0:0")
+                .and_then(|socket| socket.connect(target).map(|_| socket))
+                .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+            match socket {
+                Ok(socket) => {
+                    info!("Telemetry to udp://{} ({:?})", target, config.udp_format);
+                    Some(socket)
+                }
+                Err(err) => {
+                    warn!("Can't open telemetry socket to {}: {}", target, err);
+                    None
+                }
+            }
+        });
+
+        let file = config.file.as_ref().and_then(|path| {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => {
+                    info!("Telemetry to {} ({:?})", path, config.file_format);
+                    Some(BufWriter::new(file))
+                }
+                Err(err) => {
+                    warn!("Can't open telemetry file {}: {}", path, err);
+                    None
+                }
+            }
+        });
+
+        if udp.is_none() && file.is_none() {
+            return None;
+        }
+        Some(Self {
+            udp,
+            udp_format: config.udp_format,
+            file,
+            file_format: config.file_format,
+        })
+    }
+
+    pub fn publish(&mut self, record: &Record) {
+        if let Some(socket) = &self.udp {
+            // Нет получателя (ECONNREFUSED) или переполнен буфер — запись просто теряется
+            let _ = socket.send(&encode(record, self.udp_format));
+        }
+        if let Some(file) = self.file.as_mut()
+            && let Err(err) = file
+                .write_all(&encode(record, self.file_format))
+                .and_then(|_| file.flush())
+        {
+            warn!("Can't write telemetry: {}", err);
+            self.file = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(detections: usize) -> Record {
+        Record {
+            frame_index: 1234,
+            pts_ns: Some(5_000_000_000),
+            timestamp_us: 1_700_000_000_000_000,
+            state: TrackState::Tracking,
+            target: Some(Target {
+                track_id: 7,
+                bbox: Rect::new(10, -4, 64, 48),
+                score: 0.875,
+                source: TrackerKind::VitInt8,
+            }),
+            detections: (0..detections)
+                .map(|i| Detection {
+                    bbox: Rect::new(i as i32, 2 * i as i32, 30, 40),
+                    class_id: i as u16,
+                    confidence: 0.5,
+                })
+                .collect(),
+            latency: Latency {
+                frame: Duration::from_micros(41_250),
+                detector: Duration::ZERO,
+                tracker: Duration::from_micros(12_500),
+            },
+        }
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let tracking = record(3);
+        let searching = Record {
+            pts_ns: None,
+            state: TrackState::Searching,
+            target: None,
+            ..record(0)
+        };
+        let first = tracking.to_binary();
+        assert_eq!(first.len(), HEADER_LEN + 3 * DETECTION_LEN);
+        assert_eq!(searching.to_binary().len(), HEADER_LEN);
+
+        // Записи подряд читаются по возвращённой длине
+        let stream = [first.clone(), searching.to_binary()].concat();
+        let (decoded, used) = decode_binary(&stream).unwrap();
+        assert_eq!((decoded, used), (tracking, first.len()));
+        assert_eq!(
+            decode_binary(&stream[used..]).unwrap(),
+            (searching, HEADER_LEN)
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let tracking = record(2);
+        let json = tracking.to_json();
+        assert!(json.starts_with(&format!(
+            "{{\"version\":{},\"frame\":1234,\"pts_ns\":5000000000,",
+            SCHEMA_VERSION
+        )));
+        assert!(json.contains("\"target\":{\"track_id\":7,\"bbox\":[10,-4,64,48],\"score\":0.875,\"source\":\"vit_int8\"}"));
+        assert!(
+            json.ends_with("\"latency_us\":{\"frame\":41250,\"detector\":0,\"tracker\":12500}}")
+        );
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), tracking);
+
+        let searching = Record {
+            pts_ns: None,
+            state: TrackState::Searching,
+            target: None,
+            ..record(0)
+        };
+        let json = searching.to_json();
+        assert!(
+            json.contains("\"pts_ns\":null")
+                && json.contains("\"state\":\"searching\",\"target\":null")
+        );
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), searching);
+
+        let limited: Record = serde_json::from_str(&record(MAX_DETECTIONS + 10).to_json()).unwrap();
+        assert_eq!(limited.detections.len(), MAX_DETECTIONS);
+        let other_version = json.replacen(
+            &format!("\"version\":{}", SCHEMA_VERSION),
+            "\"version\":99",
+            1,
+        );
+        assert!(serde_json::from_str::<Record>(&other_version).is_err());
+    }
+
+    #[test]
+    fn binary_limits_detections() {
+        let (decoded, used) = decode_binary(&record(MAX_DETECTIONS + 10).to_binary()).unwrap();
+        assert_eq!(decoded.detections.len(), MAX_DETECTIONS);
+        assert_eq!(used, HEADER_LEN + MAX_DETECTIONS * DETECTION_LEN);
+    }
+
+    #[test]
+    fn binary_rejects_bad_records() {
+        let bytes = record(1).to_binary();
+        let mut other_version = bytes.clone();
+        other_version[4..6].copy_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            decode_binary(&other_version).unwrap_err(),
+            format!("Unsupported schema version {}", SCHEMA_VERSION + 1)
+        );
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(decode_binary(&bad_magic).is_err());
+        let mut bad_state = bytes.clone();
+        bad_state[6] = 9;
+        assert!(decode_binary(&bad_state).is_err());
+        assert!(decode_binary(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_binary(&bytes[..HEADER_LEN - 1]).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::config::TemplateRefreshConfig;
+use crate::utils::{BBox, iou};
+use opencv::core::Rect;
+use std::collections::VecDeque;
+
+/// Решает, когда можно переинициализировать трекер на текущей рамке,
+/// чтобы шаблон не устаревал при изменении внешнего вида цели.
+///
+/// Обновление срабатывает только если уверенность высокая, рамка стабильна
+/// несколько кадров подряд, с момента прошлого обновления прошёл cooldown
+/// и детектор подтверждает цель.
+pub struct TemplateRefresh {
+    config: TemplateRefreshConfig,
+    history: VecDeque<Rect>,
+    frames_since_refresh: u32,
+}
+
+impl TemplateRefresh {
+    pub fn new(config: &TemplateRefreshConfig) -> Self {
+        Self {
+            config: config.clone(),
+            history: VecDeque::with_capacity(config.stable_frames),
+            frames_since_refresh: 0,
+        }
+    }
+
+    /// Сбрасывает историю после (пере)инициализации трекера.
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.frames_since_refresh = 0;
+    }
+
+    /// Учитывает очередной результат трекера. `true` — пора спросить детектор.
+    pub fn observe(&mut self, bbox: Rect, score: f32) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+
+        self.frames_since_refresh = self.frames_since_refresh.saturating_add(1);
+        if score < self.config.min_score {
+            self.history.clear();
+            return false;
+        }
+
+        if self.history.len() == self.config.stable_frames.max(1) {
+            self.history.pop_front();
+        }
+        self.history.push_back(bbox);
+
+        self.frames_since_refresh >= self.config.cooldown_frames
+            && self.history.len() >= self.config.stable_frames
+            && self.is_stable()
+    }
+
+    /// Проверяет, что детектор видит объект там же, где трекер.
+    pub fn confirm(&mut self, bbox: Rect, detections: &[BBox]) -> bool {
+        let best_iou = detections
+            .iter()
+            .map(|d| iou(&bbox, &d.rect()))
+            .fold(0.0, f32::max);
+
+        // Следующая попытка — не раньше, чем через cooldown, даже если детектор не согласился
+        self.reset();
+        best_iou >= self.config.min_detector_iou
+    }
+
+    fn is_stable(&self) -> bool {
+        let Some(last) = self.history.back() else {
+            return false;
+        };
+        let diag = ((last.width * last.width + last.height * last.height) as f32).sqrt();
+        let area = (last.width * last.height) as f32;
+        if diag <= 0.0 || area <= 0.0 {
+            return false;
+        }
+
+        let center = |r: &Rect| {
+            (
+                r.x as f32 + r.width as f32 / 2.0,
+                r.y as f32 + r.height as f32 / 2.0,
+            )
+        };
+        let (cx, cy) = center(last);
+
+        self.history.iter().all(|r| {
+            let (x, y) = center(r);
+            let shift = ((x - cx).powi(2) + (y - cy).powi(2)).sqrt() / diag;
+            let scale = ((r.width * r.height) as f32 / area - 1.0).abs();
+            shift <= self.config.max_center_shift && scale <= self.config.max_scale_change
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> TemplateRefreshConfig {
+        TemplateRefreshConfig {
+            enabled: true,
+            stable_frames: 3,
+            cooldown_frames: 5,
+            ..TemplateRefreshConfig::default()
+        }
+    }
+
+    fn detection(rect: Rect) -> BBox {
+        BBox {
+            x1: rect.x as f32,
+            y1: rect.y as f32,
+            x2: (rect.x + rect.width) as f32,
+            y2: (rect.y + rect.height) as f32,
+            class_id: 0,
+            confidence: 0.9,
+        }
+    }
+
+    /// Результаты `observe` для последовательности рамок с одинаковой уверенностью.
+    fn observe_all(refresh: &mut TemplateRefresh, boxes: &[Rect], score: f32) -> Vec<bool> {
+        boxes
+            .iter()
+            .map(|&bbox| refresh.observe(bbox, score))
+            .collect()
+    }
+
+    const TARGET: Rect = Rect {
+        x: 100,
+        y: 100,
+        width: 100,
+        height: 100,
+    };
+
+    #[test]
+    fn refreshes_stable_confirmed_target() {
+        let mut refresh = TemplateRefresh::new(&config());
+        // Рамка стабильна с третьего кадра, но cooldown истекает только на пятом
+        assert_eq!(
+            observe_all(&mut refresh, &[TARGET; 5], 0.9),
+            [false, false, false, false, true]
+        );
+
+        let detections = [
+            detection(Rect::new(500, 500, 50, 50)),
+            detection(Rect::new(102, 98, 100, 100)),
+        ];
+        assert!(refresh.confirm(TARGET, &detections));
+    }
+
+    #[test]
+    fn disabled_never_refreshes() {
+        let mut refresh = TemplateRefresh::new(&TemplateRefreshConfig {
+            enabled: false,
+            ..config()
+        });
+        assert!(
+            observe_all(&mut refresh, &[TARGET; 10], 0.9)
+                .iter()
+                .all(|&ready| !ready)
+        );
+    }
+
+    #[test]
+    fn unstable_box_is_not_refreshed() {
+        // Центр уходит на 20 px при диагонали ~141 px — больше max_center_shift
+        let mut refresh = TemplateRefresh::new(&config());
+        let moving: Vec<Rect> = (0..8)
+            .map(|i| Rect::new(100 + 20 * i, 100, 100, 100))
+            .collect();
+        assert!(
+            observe_all(&mut refresh, &moving, 0.9)
+                .iter()
+                .all(|&ready| !ready)
+        );
+
+        // Площадь растёт на 10% за кадр, за окно из трёх кадров — на 21%
+        let mut refresh = TemplateRefresh::new(&config());
+        let growing: Vec<Rect> = (0..8)
+            .map(|i| Rect::new(100, 100, (100.0 * 1.1f32.powi(i)) as i32, 100))
+            .collect();
+        assert!(
+            observe_all(&mut refresh, &growing, 0.9)
+                .iter()
+                .all(|&ready| !ready)
+        );
+
+        // Небольшое дрожание в пределах допусков не мешает
+        let mut refresh = TemplateRefresh::new(&config());
+        let jitter = [
+            TARGET,
+            Rect::new(103, 98, 102, 99),
+            Rect::new(99, 101, 98, 101),
+            TARGET,
+            TARGET,
+        ];
+        assert!(observe_all(&mut refresh, &jitter, 0.9)[4]);
+    }
+
+    #[test]
+    fn low_score_clears_history() {
+        let mut refresh = TemplateRefresh::new(&config());
+        assert_eq!(observe_all(&mut refresh, &[TARGET; 4], 0.9), [false; 4]);
+        // Cooldown уже прошёл, но провал уверенности обнуляет историю
+        assert!(!refresh.observe(TARGET, 0.5));
+        assert_eq!(
+            observe_all(&mut refresh, &[TARGET; 3], 0.9),
+            [false, false, true]
+        );
+    }
+
+    #[test]
+    fn rejects_detector_disagreement() {
+        let mut refresh = TemplateRefresh::new(&config());
+        assert!(observe_all(&mut refresh, &[TARGET; 5], 0.9)[4]);
+
+        // IoU со сдвигом на 30 px: 70*100 / (2*100*100 - 70*100) ≈ 0.54 < 0.6
+        assert!(!refresh.confirm(TARGET, &[detection(Rect::new(130, 100, 100, 100))]));
+        assert!(!refresh.confirm(TARGET, &[]));
+    }
+
+    #[test]
+    fn confirm_always_resets() {
+        for detections in [vec![detection(TARGET)], vec![]] {
+            let mut refresh = TemplateRefresh::new(&config());
+            assert!(observe_all(&mut refresh, &[TARGET; 5], 0.9)[4]);
+            refresh.confirm(TARGET, &detections);
+
+            // Снова ждём и стабильности, и полного cooldown
+            assert_eq!(
+                observe_all(&mut refresh, &[TARGET; 5], 0.9),
+                [false, false, false, false, true]
+            );
+        }
+    }
+}
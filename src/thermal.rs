@@ -0,0 +1,460 @@
+use crate::config::{ThermalConfig, TrackerKind, TrackersConfig};
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermalZone {
+    /// Номер из имени каталога `thermal_zoneN`
+    pub index: usize,
+    /// Содержимое `type`, например `cpu-thermal`
+    pub kind: String,
+    pub temp_c: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuFreq {
+    pub cpu: usize,
+    pub cur_khz: u64,
+    pub max_khz: u64,
+    pub governor: String,
+}
+
+/// Читает числовой суффикс каталога: `thermal_zone3` -> 3.
+fn dir_index(path: &Path, prefix: &str) -> Option<usize> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix(prefix)?
+        .parse()
+        .ok()
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+fn indexed_dirs(dir: &Path, prefix: &str) -> Vec<(usize, PathBuf)> {
+    let mut dirs: Vec<(usize, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| Some((dir_index(&path, prefix)?, path)))
+        .collect();
+    dirs.sort_by_key(|(index, _)| *index);
+    dirs
+}
+
+/// Все `thermal_zone*` с читаемой температурой.
+pub fn read_thermal_zones(root: &Path) -> Vec<ThermalZone> {
+    indexed_dirs(&root.join("sys/class/thermal"), "thermal_zone")
+        .into_iter()
+        .filter_map(|(index, path)| {
+            // temp в миллиградусах; выключенная зона отдаёт ошибку чтения
+            let temp: i64 = read_trimmed(&path.join("temp"))?.parse().ok()?;
+            Some(ThermalZone {
+                index,
+                kind: read_trimmed(&path.join("type")).unwrap_or_default(),
+                temp_c: temp as f32 / 1000.0,
+            })
+        })
+        .collect()
+}
+
+/// Частоты ядер из `cpufreq`; ядра без драйвера частоты пропускаются.
+pub fn read_cpufreq(root: &Path) -> Vec<CpuFreq> {
+    indexed_dirs(&root.join("sys/devices/system/cpu"), "cpu")
+        .into_iter()
+        .filter_map(|(cpu, path)| {
+            let freq = path.join("cpufreq");
+            Some(CpuFreq {
+                cpu,
+                cur_khz: read_number(&freq.join("scaling_cur_freq"))?,
+                max_khz: read_number(&freq.join("scaling_max_freq")).unwrap_or(0),
+                governor: read_trimmed(&freq.join("scaling_governor")).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Самая горячая зона; 0, если датчиков нет.
+pub fn max_temp(zones: &[ThermalZone]) -> f32 {
+    zones.iter().map(|zone| zone.temp_c).fold(0.0, f32::max)
+}
+
+/// Итоговые ограничения для текущей температуры.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    /// Номер активного уровня, 0 — без ограничений
+    pub level: usize,
+    /// Детектор запускается на каждом N-м кадре, пока цель не захвачена
+    pub detect_every: u32,
+    /// Замена основного трекера
+    pub tracker: Option<TrackerKind>,
+    /// Сторона входа детектора вместо `[detector] input_size`
+    pub detector_input: Option<i32>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self {
+            level: 0,
+            detect_every: 1,
+            tracker: None,
+            detector_input: None,
+        }
+    }
+}
+
+impl Throttle {
+    /// Конфиг трекеров с учётом замены основного трекера.
+    pub fn apply(&self, trackers: &TrackersConfig) -> TrackersConfig {
+        TrackersConfig {
+            primary: self.tracker.unwrap_or(trackers.primary),
+            ..trackers.clone()
+        }
+    }
+}
+
+/// Уровни троттлинга по температуре самой горячей зоны.
+///
+/// Уровень включается при `temp_c` и выключается, только когда температура
+/// опустится ниже `temp_c - hysteresis_c`, чтобы политика не дёргалась на границе.
+/// Датчики governor сам не читает: показания ему передаёт фоновый опрос системы.
+pub struct ThermalGovernor {
+    config: ThermalConfig,
+    level: usize,
+    last_check: Option<Instant>,
+}
+
+impl ThermalGovernor {
+    pub fn new(config: &ThermalConfig) -> Self {
+        let mut config = config.clone();
+        config.levels.sort_by(|a, b| a.temp_c.total_cmp(&b.temp_c));
+        Self {
+            config,
+            level: 0,
+            last_check: None,
+        }
+    }
+
+    /// Пересчитывает уровень по температуре; возвращает ограничения всех активных уровней,
+    /// более горячие уровни переопределяют заданные ими поля.
+    pub fn update(&mut self, temp_c: f32) -> Throttle {
+        let levels = &self.config.levels;
+
+        let mut level = self.level;
+        while level < levels.len() && temp_c >= levels[level].temp_c {
+            level += 1;
+        }
+        while level > 0 && temp_c < levels[level - 1].temp_c - self.config.hysteresis_c {
+            level -= 1;
+        }
+        self.force_level(level)
+    }
+
+    /// Ограничения уровня без учёта температуры: уровень приходит из потока sysmon
+    /// или из записи при воспроизведении.
+    pub fn force_level(&mut self, level: usize) -> Throttle {
+        let levels = &self.config.levels;
+        let level = level.min(levels.len());
+        self.level = level;
+
+        let mut throttle = Throttle {
+            level,
+            ..Throttle::default()
+        };
+        for active in &levels[..level] {
+            throttle.detect_every = active.detect_every.unwrap_or(throttle.detect_every).max(1);
+            throttle.tracker = active.tracker.or(throttle.tracker);
+            throttle.detector_input = active.detector_input.or(throttle.detector_input);
+        }
+        throttle
+    }
+
+    /// Раз в `check_interval_secs` учитывает свежие показания датчиков.
+    /// Возвращает новые ограничения, только если уровень изменился.
+    pub fn observe(&mut self, zones: &[ThermalZone], freqs: &[CpuFreq]) -> Option<Throttle> {
+        if !self.config.enabled {
+            return None;
+        }
+        if self
+            .last_check
+            .is_some_and(|last| last.elapsed().as_secs_f64() < self.config.check_interval_secs)
+        {
+            return None;
+        }
+        self.last_check = Some(Instant::now());
+
+        if zones.is_empty() {
+            warn!("No thermal zones found, thermal throttling disabled");
+            self.config.enabled = false;
+            return None;
+        }
+        let temp = max_temp(zones);
+
+        let previous = self.level;
+        let throttle = self.update(temp);
+        if throttle.level == previous {
+            return None;
+        }
+
+        let hottest = zones.iter().find(|zone| zone.temp_c == temp);
+        let freqs: Vec<String> = freqs
+            .iter()
+            .map(|freq| format!("cpu{} {} MHz", freq.cpu, freq.cur_khz / 1000))
+            .collect();
+        info!(
+            "Thermal level {} -> {} at {:.1}C ({}), {}: {:?}",
+            previous,
+            throttle.level,
+            temp,
+            hottest.map_or("", |zone| zone.kind.as_str()),
+            freqs.join(", "),
+            throttle
+        );
+        Some(throttle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ThermalLevel;
+
+    /// Дерево sysfs во временном каталоге: две термозоны, выключенная зона
+    /// и два ядра, одно без cpufreq.
+    struct Sysfs {
+        root: PathBuf,
+    }
+
+    impl Sysfs {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("thermal-test-{}-{}", name, std::process::id()));
+            let write = |path: &str, text: &str| {
+                let path = root.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, text).unwrap();
+            };
+            write("sys/class/thermal/thermal_zone0/type", "cpu-thermal\n");
+            write("sys/class/thermal/thermal_zone1/type", "gpu-thermal\n");
+            write("sys/class/thermal/thermal_zone2/type", "disabled\n");
+            write("sys/class/thermal/cooling_device0/type", "fan\n");
+            for (cpu, khz) in [(0, "1800000"), (1, "600000")] {
+                let freq = format!("sys/devices/system/cpu/cpu{}/cpufreq", cpu);
+                write(&format!("{}/scaling_cur_freq", freq), khz);
+                write(&format!("{}/scaling_max_freq", freq), "1800000\n");
+                write(&format!("{}/scaling_governor", freq), "schedutil\n");
+            }
+            fs::create_dir_all(root.join("sys/devices/system/cpu/cpu2")).unwrap();
+            fs::create_dir_all(root.join("sys/devices/system/cpu/cpufreq")).unwrap();
+            let sysfs = Self { root };
+            sysfs.set_temps(45.5, 40.0);
+            sysfs
+        }
+
+        fn set_temps(&self, cpu: f32, gpu: f32) {
+            for (zone, temp) in [(0, cpu), (1, gpu)] {
+                let path = self
+                    .root
+                    .join(format!("sys/class/thermal/thermal_zone{}/temp", zone));
+                fs::write(path, format!("{}\n", (temp * 1000.0) as i64)).unwrap();
+            }
+        }
+
+        /// Показания, как их передаёт поток sysmon.
+        fn observe(&self, governor: &mut ThermalGovernor, cpu: f32) -> Option<Throttle> {
+            self.set_temps(cpu, 40.0);
+            governor.observe(&read_thermal_zones(&self.root), &read_cpufreq(&self.root))
+        }
+    }
+
+    impl Drop for Sysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    /// Уровни нарочно не по порядку: governor сортирует их сам.
+    fn config() -> ThermalConfig {
+        ThermalConfig {
+            enabled: true,
+            check_interval_secs: 0.0,
+            hysteresis_c: 5.0,
+            levels: vec![
+                ThermalLevel {
+                    temp_c: 90.0,
+                    detector_input: Some(480),
+                    ..ThermalLevel::default()
+                },
+                ThermalLevel {
+                    temp_c: 70.0,
+                    detect_every: Some(2),
+                    ..ThermalLevel::default()
+                },
+                ThermalLevel {
+                    temp_c: 80.0,
+                    detect_every: Some(4),
+                    tracker: Some(TrackerKind::VitInt8),
+                    ..ThermalLevel::default()
+                },
+            ],
+        }
+    }
+
+    fn throttle(
+        level: usize,
+        detect_every: u32,
+        tracker: Option<TrackerKind>,
+        detector_input: Option<i32>,
+    ) -> Throttle {
+        Throttle {
+            level,
+            detect_every,
+            tracker,
+            detector_input,
+        }
+    }
+
+    #[test]
+    fn reads_sensor_tree() {
+        let sysfs = Sysfs::new("read");
+        let zones = read_thermal_zones(&sysfs.root);
+        assert_eq!(
+            zones,
+            [
+                ThermalZone {
+                    index: 0,
+                    kind: "cpu-thermal".to_string(),
+                    temp_c: 45.5
+                },
+                ThermalZone {
+                    index: 1,
+                    kind: "gpu-thermal".to_string(),
+                    temp_c: 40.0
+                },
+            ]
+        );
+        assert_eq!(max_temp(&zones), 45.5);
+        assert_eq!(max_temp(&[]), 0.0);
+
+        let freqs = read_cpufreq(&sysfs.root);
+        assert_eq!(
+            freqs
+                .iter()
+                .map(|freq| (freq.cpu, freq.cur_khz))
+                .collect::<Vec<_>>(),
+            [(0, 1_800_000), (1, 600_000)]
+        );
+        assert!(
+            freqs
+                .iter()
+                .all(|freq| freq.max_khz == 1_800_000 && freq.governor == "schedutil")
+        );
+    }
+
+    #[test]
+    fn rises_through_levels() {
+        let sysfs = Sysfs::new("rise");
+        let mut governor = ThermalGovernor::new(&config());
+        assert_eq!(sysfs.observe(&mut governor, 69.9), None);
+        assert_eq!(
+            sysfs.observe(&mut governor, 70.0),
+            Some(throttle(1, 2, None, None))
+        );
+        assert_eq!(sysfs.observe(&mut governor, 75.0), None);
+        assert_eq!(
+            sysfs.observe(&mut governor, 85.0),
+            Some(throttle(2, 4, Some(TrackerKind::VitInt8), None))
+        );
+        // Верхний уровень наследует всё, что не задал сам
+        assert_eq!(
+            sysfs.observe(&mut governor, 91.0),
+            Some(throttle(3, 4, Some(TrackerKind::VitInt8), Some(480)))
+        );
+
+        // Скачок температуры проходит все уровни сразу
+        let mut governor = ThermalGovernor::new(&config());
+        assert_eq!(sysfs.observe(&mut governor, 95.0).map(|t| t.level), Some(3));
+    }
+
+    #[test]
+    fn recovers_with_hysteresis() {
+        let sysfs = Sysfs::new("recover");
+        let mut governor = ThermalGovernor::new(&config());
+        assert_eq!(sysfs.observe(&mut governor, 92.0).map(|t| t.level), Some(3));
+
+        // Уровень держится, пока температура не ниже порога минус гистерезис
+        assert_eq!(sysfs.observe(&mut governor, 86.0), None);
+        assert_eq!(sysfs.observe(&mut governor, 85.0), None);
+        assert_eq!(
+            sysfs.observe(&mut governor, 84.9),
+            Some(throttle(2, 4, Some(TrackerKind::VitInt8), None))
+        );
+        assert_eq!(sysfs.observe(&mut governor, 88.0), None);
+        assert_eq!(sysfs.observe(&mut governor, 75.0), None);
+        assert_eq!(
+            sysfs.observe(&mut governor, 66.0),
+            Some(throttle(1, 2, None, None))
+        );
+        assert_eq!(sysfs.observe(&mut governor, 65.0), None);
+        assert_eq!(
+            sysfs.observe(&mut governor, 50.0),
+            Some(Throttle::default())
+        );
+        assert_eq!(sysfs.observe(&mut governor, 50.0), None);
+    }
+
+    #[test]
+    fn check_interval_and_missing_sensors() {
+        let sysfs = Sysfs::new("interval");
+        let mut governor = ThermalGovernor::new(&ThermalConfig {
+            check_interval_secs: 3600.0,
+            ..config()
+        });
+        assert_eq!(sysfs.observe(&mut governor, 95.0).map(|t| t.level), Some(3));
+        assert_eq!(sysfs.observe(&mut governor, 50.0), None);
+
+        // Без термозон троттлинг выключается насовсем
+        let mut governor = ThermalGovernor::new(&config());
+        assert_eq!(governor.observe(&[], &[]), None);
+        assert_eq!(sysfs.observe(&mut governor, 95.0), None);
+        assert_eq!(
+            ThermalGovernor::new(&ThermalConfig {
+                enabled: false,
+                ..config()
+            })
+            .observe(&read_thermal_zones(&sysfs.root), &[]),
+            None
+        );
+    }
+
+    #[test]
+    fn forced_level_and_tracker_override() {
+        let mut governor = ThermalGovernor::new(&config());
+        assert_eq!(
+            governor.force_level(2),
+            throttle(2, 4, Some(TrackerKind::VitInt8), None)
+        );
+        assert_eq!(governor.force_level(10).level, 3);
+
+        let trackers = TrackersConfig {
+            primary: TrackerKind::Nano,
+            ..TrackersConfig::default()
+        };
+        assert_eq!(
+            governor.force_level(2).apply(&trackers).primary,
+            TrackerKind::VitInt8
+        );
+        assert_eq!(
+            governor.force_level(0).apply(&trackers).primary,
+            TrackerKind::Nano
+        );
+    }
+}
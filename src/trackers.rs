@@ -1,85 +1,131 @@
-use std::path::Path;
-use opencv::core::{Ptr, Rect, ToInputArray};
+use crate::config::{DnnConfig, TrackerKind, TrackersConfig, model_path};
+use crate::fallback_tracker::FallbackTracker;
+use crate::kcftracker::KcfTracker;
+use crate::vit_tracker::VitTracker;
+use opencv::core::{Ptr, Rect};
 use opencv::prelude::*;
-use opencv::video::{TrackerDaSiamRPN, TrackerDaSiamRPN_Params, TrackerNano, TrackerNano_Params, TrackerNano_ParamsTrait, TrackerTrait};
+use opencv::video::{
+    TrackerDaSiamRPN, TrackerDaSiamRPN_Params, TrackerNano, TrackerNano_Params,
+    TrackerNano_ParamsTrait, TrackerTrait,
+};
+use std::time::Duration;
+use ticky::Stopwatch;
 
-pub struct NanoTrack {
+#[derive(Debug, Clone, Copy)]
+pub struct TrackResult {
+    pub bbox: Rect,
+    pub score: f32,
+    /// Какая модель дала результат
+    pub source: TrackerKind,
+    pub inference_time: Duration,
+}
+
+pub trait Tracker {
+    fn init(&mut self, frame: &Mat, bbox: Rect) -> opencv::Result<()>;
+
+    /// Результат трекера без какой-либо фильтрации по порогу.
+    /// `None` — трекер сам сообщил о потере цели.
+    fn update(&mut self, frame: &Mat) -> opencv::Result<Option<TrackResult>>;
+}
+
+/// Собирает трекер по конфигу: основной и, если задан, запасной.
+/// Порог основного трекера действует и без запасного.
+pub fn create_tracker(config: &TrackersConfig) -> opencv::Result<Box<dyn Tracker>> {
+    let primary = create_single(config.primary, config)?;
+    let fallback = config
+        .fallback
+        .map(|kind| create_single(kind, config))
+        .transpose()?;
+    Ok(Box::new(FallbackTracker::new(primary, fallback, config)))
+}
+
+fn create_single(kind: TrackerKind, config: &TrackersConfig) -> opencv::Result<Box<dyn Tracker>> {
+    Ok(match kind {
+        TrackerKind::Nano => Box::new(NanoTracker::new(&config.nano)?),
+        TrackerKind::Dasiamrpn => Box::new(DaSiamRpnTracker::new(&config.dasiamrpn)?),
+        TrackerKind::Vit | TrackerKind::VitInt8 => Box::new(VitTracker::new(kind, &config.vit)?),
+        TrackerKind::Kcf => Box::new(KcfTracker::new()?),
+    })
+}
+
+pub struct NanoTracker {
     tracker: Ptr<TrackerNano>,
-    second_tracker: Ptr<TrackerDaSiamRPN>,
-    last_bbox: Option<Rect>,
 }
 
-impl NanoTrack {
-    pub fn new(initial_bbox: Rect, frame: &impl ToInputArray) -> opencv::Result<Self>
-    where
-        Self: Sized,
-    {
-        let head = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("models")
-            .join("nanotrack_head_sim.onnx");
-        let backbone = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("models")
-            .join("nanotrack_backbone_sim.onnx");
+impl NanoTracker {
+    pub fn new(config: &DnnConfig) -> opencv::Result<Self> {
+        let head = model_path("nanotrack_head_sim.onnx");
+        let backbone = model_path("nanotrack_backbone_sim.onnx");
 
         let mut param = TrackerNano_Params::default()?;
-        param.set_backbone(backbone.to_str().unwrap());
-        param.set_neckhead(head.to_str().unwrap());
+        param.set_backbone(&backbone.to_string_lossy());
+        param.set_neckhead(&head.to_string_lossy());
+        param.set_backend(config.backend.id());
+        param.set_target(config.target.id());
 
-        let mut tracker = TrackerNano::create(&param)?;
-        tracker.init(frame, initial_bbox)?;
+        let tracker = TrackerNano::create(&param)?;
+        Ok(Self { tracker })
+    }
+}
 
-        let model_siam_path = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("models")
-            .join("dasiamrpn_model.onnx");
+impl Tracker for NanoTracker {
+    fn init(&mut self, frame: &Mat, bbox: Rect) -> opencv::Result<()> {
+        self.tracker.init(frame, bbox)
+    }
+
+    fn update(&mut self, frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+        let mut sw = Stopwatch::start_new();
+        let mut bbox = Rect::default();
+        let ok = self.tracker.update(frame, &mut bbox)?;
+        let score = self.tracker.get_tracking_score()?;
+        sw.stop();
+        Ok(ok.then_some(TrackResult {
+            bbox,
+            score,
+            source: TrackerKind::Nano,
+            inference_time: sw.elapsed,
+        }))
+    }
+}
 
-        let cls1 = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("models")
-            .join("dasiamrpn_kernel_cls1.onnx");
+pub struct DaSiamRpnTracker {
+    tracker: Ptr<TrackerDaSiamRPN>,
+}
 
-        let r1 = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("models")
-            .join("dasiamrpn_kernel_r1.onnx");
+impl DaSiamRpnTracker {
+    pub fn new(config: &DnnConfig) -> opencv::Result<Self> {
+        let model = model_path("dasiamrpn_model.onnx");
+        let cls1 = model_path("dasiamrpn_kernel_cls1.onnx");
+        let r1 = model_path("dasiamrpn_kernel_r1.onnx");
 
         let mut param = TrackerDaSiamRPN_Params::default()?;
-        param.set_model(model_siam_path.to_str().unwrap());
-        param.set_kernel_cls1(cls1.to_str().unwrap());
-        param.set_kernel_r1(r1.to_str().unwrap());
-        let second_tracker = TrackerDaSiamRPN::create(&param)?;
+        param.set_model(&model.to_string_lossy());
+        param.set_kernel_cls1(&cls1.to_string_lossy());
+        param.set_kernel_r1(&r1.to_string_lossy());
+        param.set_backend(config.backend.id());
+        param.set_target(config.target.id());
+        let tracker = TrackerDaSiamRPN::create(&param)?;
+
+        Ok(Self { tracker })
+    }
+}
 
-        Ok(Self { tracker, second_tracker, last_bbox: Some(initial_bbox) })
+impl Tracker for DaSiamRpnTracker {
+    fn init(&mut self, frame: &Mat, bbox: Rect) -> opencv::Result<()> {
+        self.tracker.init(frame, bbox)
     }
 
-    pub fn update(&mut self, frame: &impl ToInputArray) -> opencv::Result<Option<Rect>> {
+    fn update(&mut self, frame: &Mat) -> opencv::Result<Option<TrackResult>> {
+        let mut sw = Stopwatch::start_new();
         let mut bbox = Rect::default();
-        // let mut sw = Stopwatch::start_new();
         let ok = self.tracker.update(frame, &mut bbox)?;
-        //sw.stop();
-        // println!("updated {}", sw.elapsed.as_millis());
-        let v = self.tracker.get_tracking_score()?;
-        println!("get tracking score: {}", v);
-        if v < 0.7 {
-            println!("init second_tracker");
-            let last_bbox = match self.last_bbox {
-                None => {bbox}
-                Some(b) => {b}
-            };
-            self.second_tracker.init(frame, last_bbox)?;
-            let ok = self.second_tracker.update(frame, &mut bbox)?;
-
-            let v = self.second_tracker.get_tracking_score()?;
-            println!("get tracking score second tracker {}", v);
-            return if v < 0.8 {
-                Ok(None)
-            } else {
-                Ok(Some(bbox))
-            }
-        }
-        if ok {
-            self.last_bbox = Some(bbox);
-            Ok(Some(bbox))
-        } else {
-            self.last_bbox = None;
-            Ok(None)
-        }
+        let score = self.tracker.get_tracking_score()?;
+        sw.stop();
+        Ok(ok.then_some(TrackResult {
+            bbox,
+            score,
+            source: TrackerKind::Dasiamrpn,
+            inference_time: sw.elapsed,
+        }))
     }
 }
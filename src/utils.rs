@@ -4,8 +4,9 @@ use opencv::core::{Rect, ToInputArray, ToInputOutputArray};
 use opencv::prelude::*;
 use opencv::{core, imgproc};
 use std::fs;
+use std::path::Path;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BBox {
     pub x1: f32,
     pub y1: f32,
@@ -15,6 +16,27 @@ pub struct BBox {
     pub confidence: f32,
 }
 
+impl BBox {
+    pub fn rect(&self) -> Rect {
+        Rect::new(
+            self.x1 as i32,
+            self.y1 as i32,
+            (self.x2 - self.x1) as i32,
+            (self.y2 - self.y1) as i32,
+        )
+    }
+
+    /// IoU без округления до целых пикселей.
+    pub fn iou(&self, other: &BBox) -> f32 {
+        let w = (self.x2.min(other.x2) - self.x1.max(other.x1)).max(0.0);
+        let h = (self.y2.min(other.y2) - self.y1.max(other.y1)).max(0.0);
+        let inter = w * h;
+        let area = |b: &BBox| (b.x2 - b.x1) * (b.y2 - b.y1);
+        let union = area(self) + area(other) - inter;
+        if union > 0.0 { inter / union } else { 0.0 }
+    }
+}
+
 pub fn mat_to_ndarray(
     frame: &impl ToInputArray,
     width: i32,
@@ -72,69 +94,42 @@ pub fn mat_to_ndarray(
     Array4::from_shape_vec((1, 3, rows, cols), out).unwrap()
 }
 
-pub fn center_crop(frame: &impl ToInputArray, crop_size: i32) -> Result<Mat> {
-    let input_array = frame.input_array().expect("frame.input_array() failed");
-    let mat = input_array
-        .get_mat(0)
-        .expect("input_array().get_mat(0) failed");
-
-    let rows = mat.rows();
-    let cols = mat.cols();
-
-    let x = (cols - crop_size) / 2;
-    let y = (rows - crop_size) / 2;
-
-    let roi = core::Rect::new(x, y, crop_size, crop_size);
-    let cropped = Mat::roi(&mat, roi).expect("roi() failed");
-
-    Ok(cropped.clone_pointee())
+/// Где кадр `width`x`height` окажется внутри квадрата `size` при вписывании с сохранением пропорций.
+pub fn letterbox_rect(width: i32, height: i32, size: i32) -> Rect {
+    let scale = (size as f32 / width as f32).min(size as f32 / height as f32);
+    let w = ((width as f32 * scale).round() as i32).clamp(1, size);
+    let h = ((height as f32 * scale).round() as i32).clamp(1, size);
+    Rect::new((size - w) / 2, (size - h) / 2, w, h)
 }
 
-/*pub fn expand_roi(frame: &impl ToInputArray, prev_roi: Rect, expand: i32) -> Result<Mat> {
-    let input_array = frame.input_array()?;
-    let mat = input_array.get_mat(0)?;
-
-    let rows = mat.rows();
-    let cols = mat.cols();
-
-    // Новый ROI с расширением
-    let mut x = prev_roi.x - expand;
-    let mut y = prev_roi.y - expand;
-    let mut w = prev_roi.width + expand * 2;
-    let mut h = prev_roi.height + expand * 2;
-
-    // Корректируем границы по размеру изображения
-    if x < 0 {
-        w += x; // уменьшаем ширину
-        x = 0;
-    }
-    if y < 0 {
-        h += y; // уменьшаем высоту
-        y = 0;
-    }
-    if x + w > cols {
-        w = cols - x;
-    }
-    if y + h > rows {
-        h = rows - y;
-    }
-
-    // Если размеры ушли в ноль или отрицательные → пустой ROI
-    if w <= 0 || h <= 0 {
-        return Ok(mat);
-        // return Err(opencv::Error::new(
-        //     opencv::core::StsBadArg,
-        //     "Expanded ROI is invalid".to_string(),
-        // ));
-    }
-
-    let roi = Rect::new(x, y, w, h);
-    let cropped = Mat::roi(&mat, roi)?;
+/// Вписывает кадр в квадрат `size`, поля серые, как при обучении YOLO.
+pub fn letterbox(frame: &Mat, size: i32) -> Result<Mat> {
+    let placed = letterbox_rect(frame.cols(), frame.rows(), size);
+    let mut resized = Mat::default();
+    imgproc::resize(frame, &mut resized, placed.size(), 0.0, 0.0, imgproc::INTER_LINEAR)?;
 
-    Ok(cropped.clone_pointee())
-}*/
+    let mut boxed = Mat::default();
+    core::copy_make_border(
+        &resized,
+        &mut boxed,
+        placed.y,
+        size - placed.height - placed.y,
+        placed.x,
+        size - placed.width - placed.x,
+        core::BORDER_CONSTANT,
+        core::Scalar::all(114.0),
+    )?;
+    Ok(boxed)
+}
 
-pub fn draw_bboxes(frame: &mut Mat, bboxes: &[BBox], labels: &[&str]) -> opencv::Result<()> {
+pub fn draw_bboxes(
+    frame: &mut Mat,
+    bboxes: &[BBox],
+    labels: &[&str],
+    color: core::Scalar,
+    font_scale: f64,
+    thickness: i32,
+) -> opencv::Result<()> {
     for bbox in bboxes {
         let rect = core::Rect {
             x: bbox.x1 as i32,
@@ -146,21 +141,22 @@ pub fn draw_bboxes(frame: &mut Mat, bboxes: &[BBox], labels: &[&str]) -> opencv:
         imgproc::rectangle(
             frame,
             rect,
-            core::Scalar::new(0.0, 255.0, 0.0, 0.0),
-            1,
+            color,
+            thickness,
             opencv::imgproc::LINE_8,
             0,
         )?;
 
-        let label = format!("{} -> {:.2}", labels[bbox.class_id], bbox.confidence);
+        let name = labels.get(bbox.class_id).copied().unwrap_or("?");
+        let label = format!("{} -> {:.2}", name, bbox.confidence);
         imgproc::put_text(
             frame,
             &label,
             core::Point::new(rect.x, rect.y - 5),
             imgproc::FONT_HERSHEY_SIMPLEX,
-            0.5,
-            core::Scalar::new(0.0, 255.0, 0.0, 0.0),
-            1,
+            font_scale,
+            color,
+            thickness,
             imgproc::LINE_AA,
             false,
         )?;
@@ -182,18 +178,10 @@ pub fn iou(a: &Rect, b: &Rect) -> f32 {
     inter_area / (a_area + b_area - inter_area)
 }
 
-pub fn get_cpu_usage() -> f32 {
-    let loadavg = fs::read_to_string("/proc/loadavg").expect("Can't read /proc/loadavg");
-    let parts: Vec<&str> = loadavg.trim().split(' ').collect();
-    if let Some(cpu_usage) = parts.get(0) {
-        return cpu_usage.parse::<f32>().unwrap_or(0.0) * 100.0 / 4.0;
-    }
-
-    0.0
-}
-
-pub fn get_mem_usage() -> f32 {
-    let mem_info = fs::read_to_string("/proc/meminfo").expect("Can't read /proc/meminfo");
+/// Занятая память в процентах по `proc/meminfo` относительно `root`;
+/// `None`, если файл не прочитать или в нём нет `MemTotal`.
+pub fn get_mem_usage(root: &Path) -> Option<f32> {
+    let mem_info = fs::read_to_string(root.join("proc/meminfo")).ok()?;
     let mut total: f32 = 0.0;
     let mut free: f32 = 0.0;
 
@@ -216,17 +204,7 @@ pub fn get_mem_usage() -> f32 {
         }
     }
 
-    if total > 0.0 {
-        return (1.0 - free / total) * 100.0;
-    }
-
-    0.0
-}
-
-pub fn get_cpu_temp() -> f32 {
-    let temp_str = fs::read_to_string("/sys/class/thermal/thermal_zone0/temp").unwrap_or_default();
-    let temp_milli: f32 = temp_str.trim().parse::<f32>().unwrap_or(0.0);
-    temp_milli / 1000.0
+    (total > 0.0).then(|| (1.0 - free / total) * 100.0)
 }
 
 pub fn expand_roi_rect(frame: &impl ToInputArray, prev_roi: Rect, expand: i32) -> Result<Rect> {
@@ -289,11 +267,3 @@ pub fn expand_roi_rect(frame: &impl ToInputArray, prev_roi: Rect, expand: i32) -
 
     Ok(Rect::new(nx, ny, nw, nh))
 }
-
-pub fn expand_roi(frame: &impl ToInputArray, prev_roi: Rect, expand: i32) -> Result<(Mat, Rect)> {
-    let roi = expand_roi_rect(frame, prev_roi, expand)?;
-    let input_array = frame.input_array()?;
-    let mat = input_array.get_mat(0)?;
-    let cropped = Mat::roi(&mat, roi)?;
-    Ok((cropped.clone_pointee(), roi))
-}
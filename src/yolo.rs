@@ -1,22 +1,128 @@
+use std::num::NonZeroUsize;
 use std::path::Path;
-use ndarray::{s, Axis};
-use crate::utils::BBox;
+use ndarray::{s, Array4, ArrayD, Axis};
+use crate::config::{model_path, DetectorConfig, ExecutionMode, ExecutionProvider, Preprocess};
+use crate::utils::{letterbox, letterbox_rect, mat_to_ndarray, BBox};
+use log::info;
+use opencv::core::Mat;
+use opencv::prelude::*;
+use ort::execution_providers::{
+    ACLExecutionProvider, ArmNNExecutionProvider, CPUExecutionProvider, ExecutionProviderDispatch,
+    XNNPACKExecutionProvider,
+};
+use ort::session::builder::GraphOptimizationLevel;
 use ort::session::Session;
 use ort::value::TensorRef;
 
+/// Классы COCO в порядке выходов модели.
+pub const COCO_CLASSES: [&str; 80] = [
+    "person", "bicycle", "car", "motorcycle", "airplane", "bus", "train", "truck", "boat",
+    "traffic light", "fire hydrant", "stop sign", "parking meter", "bench", "bird", "cat", "dog",
+    "horse", "sheep", "cow", "elephant", "bear", "zebra", "giraffe", "backpack", "umbrella",
+    "handbag", "tie", "suitcase", "frisbee", "skis", "snowboard", "sports ball", "kite",
+    "baseball bat", "baseball glove", "skateboard", "surfboard", "tennis racket", "bottle",
+    "wine glass", "cup", "fork", "knife", "spoon", "bowl", "banana", "apple", "sandwich", "orange",
+    "broccoli", "carrot", "hot dog", "pizza", "donut", "cake", "chair", "couch", "potted plant",
+    "bed", "dining table", "toilet", "tv", "laptop", "mouse", "remote", "keyboard", "cell phone",
+    "microwave", "oven", "toaster", "sink", "refrigerator", "book", "clock", "vase", "scissors",
+    "teddy bear", "hair drier", "toothbrush",
+];
+
+/// Перевод координат входа сети в координаты кадра: `(x - pad_x) * scale_x`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputTransform {
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub pad_x: f32,
+    pub pad_y: f32,
+}
+
+impl InputTransform {
+    pub fn new(preprocess: Preprocess, input_size: i32, width: i32, height: i32) -> Self {
+        match preprocess {
+            Preprocess::Stretch => Self {
+                scale_x: width as f32 / input_size as f32,
+                scale_y: height as f32 / input_size as f32,
+                pad_x: 0.0,
+                pad_y: 0.0,
+            },
+            Preprocess::Letterbox => {
+                let placed = letterbox_rect(width, height, input_size);
+                Self {
+                    scale_x: width as f32 / placed.width as f32,
+                    scale_y: height as f32 / placed.height as f32,
+                    pad_x: placed.x as f32,
+                    pad_y: placed.y as f32,
+                }
+            }
+        }
+    }
+}
+
+/// Подавление немаксимумов отдельно по каждому классу.
+pub fn nms(mut boxes: Vec<BBox>, iou_threshold: f32) -> Vec<BBox> {
+    boxes.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let mut kept: Vec<BBox> = Vec::with_capacity(boxes.len());
+    for bbox in boxes {
+        if kept.iter().all(|k| k.class_id != bbox.class_id || k.iou(&bbox) <= iou_threshold) {
+            kept.push(bbox);
+        }
+    }
+    kept
+}
+
 pub struct YoloV8 {
     session: Session,
+    pub preprocess: Preprocess,
+    pub confidence_threshold: f32,
+    pub nms_iou_threshold: Option<f32>,
+    /// Сторона входа, зашитая в модель; `None` — вход с динамическим размером.
+    pub input_size: Option<i32>,
 }
 
 impl YoloV8 {
-    pub fn new() -> ort::Result<Self> {
-        let model_path = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("models")
-            .join("yolov8n.onnx");
-        let session = Session::builder()?
-            .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-            .commit_from_file(model_path)?;
-        Ok(Self { session })
+    pub fn new(config: &DetectorConfig) -> ort::Result<Self> {
+        let model = model_path(&config.model);
+
+        let mut builder = Session::builder()?
+            .with_intra_op_spinning(config.intra_op_spinning)?
+            .with_parallel_execution(config.execution_mode == ExecutionMode::Parallel)?
+            .with_execution_providers(execution_providers(config))?;
+        if config.intra_threads > 0 {
+            builder = builder.with_intra_threads(config.intra_threads)?;
+        }
+        if config.inter_threads > 0 {
+            builder = builder.with_inter_threads(config.inter_threads)?;
+        }
+
+        // Если кэш свежее исходной модели — грузим его без повторной оптимизации
+        let session = match config.optimized_model_cache.as_deref().map(model_path) {
+            Some(cache) if is_fresh_cache(&cache, &model) => {
+                info!("Load optimized detector model from {}", cache.display());
+                builder
+                    .with_optimization_level(GraphOptimizationLevel::Disable)?
+                    .commit_from_file(cache)?
+            }
+            Some(cache) => builder
+                .with_optimization_level(GraphOptimizationLevel::Level3)?
+                .with_optimized_model_path(cache)?
+                .commit_from_file(&model)?,
+            None => builder
+                .with_optimization_level(GraphOptimizationLevel::Level3)?
+                .commit_from_file(&model)?,
+        };
+        let input_size = static_input_size(&session);
+        match input_size {
+            Some(size) => info!("Detector model has static input {}x{}", size, size),
+            None => info!("Detector model has dynamic input"),
+        }
+        Ok(Self {
+            session,
+            input_size,
+            preprocess: config.preprocess,
+            confidence_threshold: config.confidence_threshold,
+            nms_iou_threshold: config.nms_iou_threshold,
+        })
     }
     
     pub fn infer2(
@@ -24,40 +130,62 @@ impl YoloV8 {
         input: &ndarray::Array<f32, ndarray::Dim<[usize; 4]>>,
         img_width: i32,
         img_height: i32,
-    ) -> Vec<BBox> {
-        //let mut sw = Stopwatch::start_new();
-        let outputs = self
-            .session
-            .run(ort::inputs!["images" => TensorRef::from_array_view(input).unwrap()])
-            .unwrap();
-        //sw.stop();
-        //println!("stop run: {:?}", sw.elapsed().as_millis());
-
-        //sw.restart();
-        let output = outputs["output0"]
-            .try_extract_array::<f32>()
-            .unwrap()
-            .t()
-            .into_owned();
-        //sw.stop();
-        //println!("stop try_extract_array: {:?}", sw.elapsed().as_millis());
+    ) -> ort::Result<Vec<BBox>> {
+        let output = self.inference(input)?;
+        let transform = InputTransform::new(Preprocess::Stretch, input.shape()[3] as i32, img_width, img_height);
+        Ok(self.decode(&output, &transform))
+    }
+
+    /// Кадр -> вход сети стороной `input_size` и перевод координат выхода обратно в кадр.
+    pub fn prepare(&self, frame: &Mat, input_size: i32) -> (Array4<f32>, InputTransform) {
+        let transform = InputTransform::new(self.preprocess, input_size, frame.cols(), frame.rows());
+        let input = match self.preprocess {
+            Preprocess::Stretch => mat_to_ndarray(frame, input_size, input_size),
+            Preprocess::Letterbox => {
+                let boxed = letterbox(frame, input_size).expect("Can't letterbox frame");
+                mat_to_ndarray(&boxed, input_size, input_size)
+            }
+        };
+        (input, transform)
+    }
 
+    /// Рамки в координатах кадра после порога уверенности и NMS из конфига.
+    pub fn decode(&self, output: &ArrayD<f32>, transform: &InputTransform) -> Vec<BBox> {
+        let boxes = Self::postprocess(output, transform, self.confidence_threshold);
+        match self.nms_iou_threshold {
+            Some(threshold) => nms(boxes, threshold),
+            None => boxes,
+        }
+    }
 
+    /// Прогон сети; выход транспонирован в `[anchors, 4 + classes, batch]`.
+    pub fn inference(&mut self, input: &ndarray::Array<f32, ndarray::Dim<[usize; 4]>>) -> ort::Result<ArrayD<f32>> {
+        // Статическая модель на другом размере падает внутри ORT с невнятной ошибкой
+        if let Some(size) = self.input_size {
+            let (height, width) = (input.shape()[2], input.shape()[3]);
+            if (height, width) != (size as usize, size as usize) {
+                return Err(ort::Error::new(format!(
+                    "input {}x{} doesn't match static model input {}x{}",
+                    width, height, size, size
+                )));
+            }
+        }
+        let outputs = self.session.run(ort::inputs!["images" => TensorRef::from_array_view(input)?])?;
+        Ok(outputs["output0"].try_extract_array::<f32>()?.t().into_owned())
+    }
+
+    /// Все рамки с уверенностью не ниже `confidence_threshold`, без NMS.
+    pub fn postprocess(output: &ArrayD<f32>, transform: &InputTransform, confidence_threshold: f32) -> Vec<BBox> {
         let mut boxes = Vec::<BBox>::new();
 
-        //sw.restart();
         let output = output.slice(s![..,..,0]);
-        //sw.stop();
-        //println!("stop slice: {:?}", sw.elapsed().as_millis());
-
-        //sw.restart();
 
         for row in output.axis_iter(Axis(0)) {
             // первые 4 значения — bbox
-            let xc = row[0usize] / 640.0 * (img_width as f32);
-            let yc = row[1usize] / 640.0 * (img_height as f32);
-            let w  = row[2usize] / 640.0 * (img_width as f32);
-            let h  = row[3usize] / 640.0 * (img_height as f32);
+            let xc = (row[0usize] - transform.pad_x) * transform.scale_x;
+            let yc = (row[1usize] - transform.pad_y) * transform.scale_y;
+            let w  = row[2usize] * transform.scale_x;
+            let h  = row[3usize] * transform.scale_y;
 
             // ищем максимум среди классов (начиная с индекса 4)
             let mut best_class = 0;
@@ -69,25 +197,62 @@ impl YoloV8 {
                 }
             }
 
-            if best_prob < 0.5 {
+            if best_prob < confidence_threshold {
                 continue;
             }
 
-            // if best_class == 4 || best_class == 8 || best_class == 2 || best_class == 5 || best_class == 7 {
-                boxes.push(BBox {
-                    x1: xc - w / 2.0,
-                    y1: yc - h / 2.0,
-                    x2: xc + w / 2.0,
-                    y2: yc + h / 2.0,
-                    class_id: best_class,
-                    confidence: best_prob,
-                });
-            // }
+            boxes.push(BBox {
+                x1: xc - w / 2.0,
+                y1: yc - h / 2.0,
+                x2: xc + w / 2.0,
+                y2: yc + h / 2.0,
+                class_id: best_class,
+                confidence: best_prob,
+            });
         }
 
-        //sw.stop();
-        //println!("stop foreach: {:?}", sw.elapsed().as_millis());
-        //println!("================================================");
         boxes
     }
 }
+
+fn execution_providers(config: &DetectorConfig) -> Vec<ExecutionProviderDispatch> {
+    let mut providers: Vec<ExecutionProviderDispatch> = config
+        .providers
+        .iter()
+        .map(|provider| match provider {
+            ExecutionProvider::Cpu => CPUExecutionProvider::default().build(),
+            ExecutionProvider::Xnnpack => {
+                let mut xnnpack = XNNPACKExecutionProvider::default();
+                if let Some(threads) = NonZeroUsize::new(config.intra_threads) {
+                    xnnpack = xnnpack.with_intra_op_num_threads(threads);
+                }
+                xnnpack.build()
+            }
+            ExecutionProvider::Acl => ACLExecutionProvider::default().build(),
+            ExecutionProvider::Armnn => ArmNNExecutionProvider::default().build(),
+        })
+        .collect();
+
+    // Провайдеры, не собранные в ORT, пропускаются; CPU — последний запасной вариант
+    if !config.providers.contains(&ExecutionProvider::Cpu) {
+        providers.push(CPUExecutionProvider::default().build());
+    }
+    providers
+}
+
+/// Сторона квадратного входа `[N, C, H, W]`, если она зашита в модель (`-1` — динамическое измерение).
+fn static_input_size(session: &Session) -> Option<i32> {
+    let shape = session.inputs.first()?.input_type.tensor_shape()?;
+    match shape.get(2..4)? {
+        &[height, width] if height > 0 && height == width => Some(height as i32),
+        _ => None,
+    }
+}
+
+fn is_fresh_cache(cache: &Path, model: &Path) -> bool {
+    let modified = |p: &Path| p.metadata().and_then(|m| m.modified()).ok();
+    match (modified(cache), modified(model)) {
+        (Some(cache), Some(model)) => cache >= model,
+        _ => false,
+    }
+}
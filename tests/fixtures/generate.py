@@ -0,0 +1,78 @@
+#!/usr/bin/env python3
+"""Генерирует синтетические последовательности для регрессионных тестов трекеров.
+
+Формат OTB: img/0001.png ... и groundtruth_rect.txt со строками x,y,w,h.
+Только стандартная библиотека, результат детерминирован.
+"""
+import math
+import os
+import struct
+import zlib
+
+WIDTH, HEIGHT, FRAMES = 128, 96, 30
+
+
+def png(path, pixels):
+    raw = b"".join(b"\x00" + bytes(row) for row in pixels)
+
+    def chunk(kind, data):
+        body = kind + data
+        return struct.pack(">I", len(data)) + body + struct.pack(">I", zlib.crc32(body))
+
+    header = struct.pack(">IIBBBBB", WIDTH, HEIGHT, 8, 0, 0, 0, 0)
+    with open(path, "wb") as f:
+        f.write(b"\x89PNG\r\n\x1a\n" + chunk(b"IHDR", header)
+                + chunk(b"IDAT", zlib.compress(raw, 9)) + chunk(b"IEND", b""))
+
+
+def background(x, y):
+    return int(110 + 40 * math.sin(x / 9.0) * math.cos(y / 13.0) + 20 * ((x // 16 + y // 16) % 2))
+
+
+def target(u, v):
+    """Текстура цели в нормированных координатах 0..1: шахматка с тёмным кольцом."""
+    cell = (int(u * 4) + int(v * 4)) % 2
+    r = math.hypot(u - 0.5, v - 0.5)
+    if 0.25 < r < 0.35:
+        return 20
+    return 235 if cell else 60
+
+
+def render(box):
+    x0, y0, w, h = box
+    pixels = []
+    for y in range(HEIGHT):
+        row = []
+        for x in range(WIDTH):
+            if x0 <= x < x0 + w and y0 <= y < y0 + h:
+                row.append(target((x - x0 + 0.5) / w, (y - y0 + 0.5) / h))
+            else:
+                row.append(background(x, y))
+        pixels.append(row)
+    return pixels
+
+
+def translate(i):
+    t = i / (FRAMES - 1)
+    return (int(20 + 70 * t), int(36 + 20 * math.sin(t * 2 * math.pi)), 24, 20)
+
+
+def scale(i):
+    t = i / (FRAMES - 1)
+    w, h = int(20 + 16 * t), int(16 + 12 * t)
+    return (int(54 - w / 2 + 10 * t), int(46 - h / 2), w, h)
+
+
+def write(name, motion):
+    root = os.path.join(os.path.dirname(os.path.abspath(__file__)), name)
+    os.makedirs(os.path.join(root, "img"), exist_ok=True)
+    boxes = [motion(i) for i in range(FRAMES)]
+    for i, box in enumerate(boxes):
+        png(os.path.join(root, "img", "%04d.png" % (i + 1)), render(box))
+    with open(os.path.join(root, "groundtruth_rect.txt"), "w") as f:
+        f.writelines("%d,%d,%d,%d\n" % box for box in boxes)
+
+
+if __name__ == "__main__":
+    write("translate", translate)
+    write("scale", scale)